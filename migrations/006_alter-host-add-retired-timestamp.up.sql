@@ -0,0 +1 @@
+ALTER TABLE host ADD COLUMN retired_at TIMESTAMPTZ;
@@ -0,0 +1,17 @@
+CREATE TABLE annotation (
+    annotation_id BIGSERIAL PRIMARY KEY,
+    hostname TEXT NOT NULL,
+    FOREIGN KEY (hostname)
+        REFERENCES host(hostname)
+        ON UPDATE CASCADE ON DELETE CASCADE,
+    activation_id BIGINT,
+    FOREIGN KEY (activation_id)
+        REFERENCES activation(activation_id)
+        ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ
+);
+CREATE INDEX IdxAnnotationHostname ON annotation (hostname);
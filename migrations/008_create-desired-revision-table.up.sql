@@ -0,0 +1,11 @@
+CREATE TABLE desired_revision (
+    grouping_key TEXT NOT NULL,
+    group_value TEXT NOT NULL,
+    commit_hash TEXT,
+    store_path TEXT,
+    deadline TIMESTAMPTZ,
+    author TEXT NOT NULL,
+    set_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (grouping_key, group_value),
+    CHECK ((commit_hash IS NULL) <> (store_path IS NULL))
+);
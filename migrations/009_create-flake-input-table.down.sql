@@ -0,0 +1 @@
+DROP TABLE flake_input;
@@ -0,0 +1,7 @@
+CREATE TABLE flake_input (
+    store_path TEXT NOT NULL REFERENCES nix_store_path (store_path) ON DELETE CASCADE,
+    input_name TEXT NOT NULL,
+    revision TEXT NOT NULL,
+    PRIMARY KEY (store_path, input_name)
+);
+CREATE INDEX IdxFlakeInputNameRevision ON flake_input (input_name, revision);
@@ -0,0 +1,6 @@
+CREATE TABLE package (
+    store_path TEXT NOT NULL REFERENCES nix_store_path (store_path) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    PRIMARY KEY (store_path, name, version)
+);
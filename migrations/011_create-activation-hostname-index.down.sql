@@ -0,0 +1 @@
+DROP INDEX IdxActivationHostnameActivatedAt;
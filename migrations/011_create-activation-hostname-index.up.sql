@@ -0,0 +1 @@
+CREATE INDEX IdxActivationHostnameActivatedAt ON activation (hostname, activated_at);
@@ -39,6 +39,20 @@ in
                 default = { };
                 description = "metadata (keys are strings and values are strings).";
               };
+              collector = lib.mkOption {
+                type = types.enum [
+                  "http"
+                  "command"
+                ];
+                default = "http";
+                description = "how to collect the activation log, from the activation logger over http or by running the collector command";
+              };
+              collector_command = lib.mkOption {
+                type = types.nullOr types.str;
+                default = null;
+                example = "ssh {host_url} cat /var/log/hostmap-activation-logs/hostmap-activation-logs.csv";
+                description = "overrides the scrapers collector command for this host";
+              };
             };
           }
         );
@@ -78,6 +92,32 @@ in
         type = types.port;
         description = "port to scrape the activation logger on server to listen on";
       };
+      collectorCommand = mkOption {
+        type = types.str;
+        default = "ssh {host_url} cat ${activationLogFile}";
+        description = "command printing the activation log for hosts using the command collector. {host_url} and {hostname} are replaced";
+      };
+      collectorCommandTimeout = mkOption {
+        type = types.int;
+        default = 30;
+        description = "seconds before a collector command is killed";
+      };
+      maxConcurrentCommands = mkOption {
+        type = types.int;
+        default = 4;
+        description = "number of collector commands allowed to run at the same time";
+      };
+      spoolMaxBytes = mkOption {
+        type = types.int;
+        default = 256 * 1024 * 1024;
+        description = "size in bytes the spool of payloads waiting for the server may grow to before the oldest are dropped";
+      };
+      metricsListen = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "127.0.0.1:9101";
+        description = "address to serve prometheus metrics about the scraper on. metrics are not served if null";
+      };
     };
     server = {
       enable = mkEnableOption "hostmap server";
@@ -87,8 +127,27 @@ in
         description = "a database url for the server";
       };
       repoUrl = mkOption {
-        type = types.str;
-        description = "url to the git remote repo, e.g. https://gitlab.dbc.dk/platform/deployments/-/commit";
+        type = types.nullOr types.str;
+        default = null;
+        description = "url to the git remote repo, e.g. https://gitlab.dbc.dk/platform/deployments/-/commit. used for commit links when commitUrlTemplate is null";
+      };
+      commitUrlTemplate = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "https://gitlab.dbc.dk/platform/deployments/-/commit/{commit}";
+        description = "url of a commit, {commit} is replaced by the hash";
+      };
+      compareUrlTemplate = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "https://gitlab.dbc.dk/platform/deployments/-/compare/{from}...{to}";
+        description = "url comparing two commits, {from} and {to} are replaced by the hashes. no compare links if null";
+      };
+      branchUrlTemplate = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "https://gitlab.dbc.dk/platform/deployments/-/tree/{branch}";
+        description = "url of a branch, {branch} is replaced by its name. no branch links if null";
       };
 
       columns = mkOption {
@@ -96,9 +155,15 @@ in
         default = [ ];
         description = "columns to show from metadata";
       };
+      inputColumns = mkOption {
+        type = types.listOf types.str;
+        default = [ "nixpkgs" ];
+        description = "flake inputs whose revision in the running system is shown as a column";
+      };
       groupingKey = mkOption {
         type = types.str;
-        description = "the default grouping of the hosts on index page";
+        example = "environment,host_group_name";
+        description = "the default grouping of the hosts on index page, comma separated metadata keys nest the groups in that order";
       };
       port = mkOption {
         type = types.port;
@@ -115,6 +180,32 @@ in
         default = "UTC";
         example = "Europe/Copenhagen";
       };
+      branchPrecedence = mkOption {
+        type = types.listOf types.str;
+        default = [ "master" ];
+        example = [
+          "main"
+          "release/*"
+        ];
+        description = "branch names or globs in order of precedence, used to pick the revision shown for a store path built from several commits";
+      };
+      unknownProvenanceWebhook = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        description = "url the server posts activations of store paths not linked to a commit by CI to";
+      };
+      gitRepoPath = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "/var/lib/hostmap/deployments";
+        description = "local checkout of the repo the systems are built from, readable by the hostmap user, for commit messages and the commits between activations. keep it fetched, e.g. with a timer";
+      };
+      gitDefaultBranch = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "origin/main";
+        description = "branch of gitRepoPath to count commits ahead and behind against, HEAD of the checkout if null";
+      };
     };
   };
 
@@ -143,11 +234,15 @@ in
           RUST_LOG = "info";
         };
 
+        # used by the default collector command
+        path = [ pkgs.openssh ];
+
         serviceConfig = {
           User = "hostmap";
           Group = "hostmap";
           Restart = "always";
           RestartSec = 30;
+          StateDirectory = "hostmap-scraper";
           ExecStart = ''
             ${pkgs.hostmap}/bin/hostmap scraper \
             --hosts-file ${pkgs.writeText "targetHosts.json" (builtins.toJSON cfg.scraper.targetHosts)} \
@@ -155,6 +250,12 @@ in
             --api-key-file ${cfg.scraper.apiKeyFile} \
             --url ${cfg.scraper.serverUrl} \
             --activation-logger-port "${toString cfg.scraper.activationLoggerPort}" \
+            --collector-command ${lib.escapeShellArg cfg.scraper.collectorCommand} \
+            --collector-command-timeout ${toString cfg.scraper.collectorCommandTimeout} \
+            --max-concurrent-commands ${toString cfg.scraper.maxConcurrentCommands} \
+            --spool-dir /var/lib/hostmap-scraper/spool \
+            --spool-max-bytes ${toString cfg.scraper.spoolMaxBytes} \
+            ${lib.optionalString (cfg.scraper.metricsListen != null) "--metrics-listen ${cfg.scraper.metricsListen}"} \
             --concurrent-requests ${toString cfg.scraper.concurrentRequests}'';
         };
       };
@@ -219,9 +320,17 @@ in
               --url 127.0.0.1 \
               --port ${port} \
               --database-url ${cfg.server.databaseUrl} \
-              --repo-url ${cfg.server.repoUrl} \
+              ${lib.optionalString (cfg.server.repoUrl != null) "--repo-url ${cfg.server.repoUrl}"} \
+              ${lib.optionalString (cfg.server.commitUrlTemplate != null) "--commit-url-template ${lib.escapeShellArg cfg.server.commitUrlTemplate}"} \
+              ${lib.optionalString (cfg.server.compareUrlTemplate != null) "--compare-url-template ${lib.escapeShellArg cfg.server.compareUrlTemplate}"} \
+              ${lib.optionalString (cfg.server.branchUrlTemplate != null) "--branch-url-template ${lib.escapeShellArg cfg.server.branchUrlTemplate}"} \
               --grouping-key ${cfg.server.groupingKey} \
+              --branch-precedence ${lib.escapeShellArg (lib.concatStringsSep "," cfg.server.branchPrecedence)} \
               --api-key-file ${cfg.server.apiKeyFile} \
+              ${lib.optionalString (cfg.server.unknownProvenanceWebhook != null) "--unknown-provenance-webhook ${cfg.server.unknownProvenanceWebhook}"} \
+              ${lib.optionalString (cfg.server.gitRepoPath != null) "--git-repo-path ${cfg.server.gitRepoPath}"} \
+              ${lib.optionalString (cfg.server.gitDefaultBranch != null) "--git-default-branch ${lib.escapeShellArg cfg.server.gitDefaultBranch}"} \
+              --input-columns ${lib.escapeShellArg (lib.concatStringsSep "," cfg.server.inputColumns)} \
               --columns "${cols}" '';
         };
       };
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::{Args, Parser, Subcommand, command};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(name = "app")]
@@ -39,9 +39,10 @@ pub struct ServerArgs {
     pub api_key_file: PathBuf,
     #[arg(
         long = "grouping-key",
-        help = "Default key from metadata to group by on the frontpage"
+        value_delimiter = ',',
+        help = "Default comma separated keys from metadata to group by on the frontpage, e.g. environment,host_group_name nests the groups in that order"
     )]
-    pub default_grouping_key: Option<String>,
+    pub default_grouping_keys: Vec<String>,
     #[arg(long, default_value = "127.0.0.1", help = "url run the server on")]
     pub url: String,
     #[arg(long, default_value = "3000", help = "port to run server on")]
@@ -50,9 +51,54 @@ pub struct ServerArgs {
     pub columns: Option<Vec<String>>,
     #[arg(
         long,
-        help = "link to git repo(github, gitlab, etc), commit hash will be appended, https://github.com/foo-user/repo-name/commit"
+        value_delimiter = ',',
+        default_value = "nixpkgs",
+        help = "comma separated flake inputs whose revision in the running system is shown as a column in the frontpage table"
     )]
-    pub repo_url: String,
+    pub input_columns: Vec<String>,
+    #[arg(
+        long,
+        required_unless_present = "commit_url_template",
+        help = "link to git repo(github, gitlab, etc), commit hash will be appended, https://github.com/foo-user/repo-name/commit. --commit-url-template takes precedence"
+    )]
+    pub repo_url: Option<String>,
+    #[arg(
+        long,
+        help = "url of a commit with {commit} in place of the hash, e.g. https://gitlab.example.com/group/repo/-/commit/{commit} or https://cgit.example.com/repo/commit/?id={commit}"
+    )]
+    pub commit_url_template: Option<String>,
+    #[arg(
+        long,
+        help = "url comparing two commits with {from} and {to} in place of the hashes, e.g. https://github.com/foo-user/repo-name/compare/{from}...{to}"
+    )]
+    pub compare_url_template: Option<String>,
+    #[arg(
+        long,
+        help = "url of a branch with {branch} in place of its name, e.g. https://github.com/foo-user/repo-name/tree/{branch}"
+    )]
+    pub branch_url_template: Option<String>,
+    #[arg(
+        long,
+        help = "url to post activations of store paths not linked to a commit by CI to, when they are received"
+    )]
+    pub unknown_provenance_webhook: Option<String>,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "master",
+        help = "comma separated branch names or globs, e.g. main,release/*. when a store path was built from several commits, the one on the earliest matching branch is shown"
+    )]
+    pub branch_precedence: Vec<String>,
+    #[arg(
+        long,
+        help = "local checkout of the repo the systems are built from, for commit messages, authors and the commits between activations. keep it fetched, e.g. with a timer"
+    )]
+    pub git_repo_path: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "branch of --git-repo-path to count commits ahead and behind against, e.g. origin/main. HEAD of the checkout if unset"
+    )]
+    pub git_default_branch: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -80,6 +126,76 @@ pub struct ScraperArgs {
         help = "url of server to send scraped activations to(the server you started with `hostmap server ...`"
     )]
     pub url: String,
+    #[arg(
+        long,
+        help = "scrape every host a single time, print a summary and exit instead of looping forever"
+    )]
+    pub once: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SummaryFormat::Table,
+        help = "format of the summary printed by --once"
+    )]
+    pub summary_format: SummaryFormat,
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        value_parser = parse_ratio,
+        help = "highest ratio (0.0-1.0) of failed hosts accepted by --once before it exits with a non-zero status"
+    )]
+    pub max_failure_ratio: f64,
+    #[arg(
+        long,
+        default_value = "ssh {host_url} cat /var/log/hostmap-activation-logs/hostmap-activation-logs.csv",
+        help = "command printing the activation log csv for hosts with `\"collector\": \"command\"`. {host_url} and {hostname} are replaced"
+    )]
+    pub collector_command: String,
+    #[arg(
+        long,
+        default_value_t = 30,
+        help = "seconds before a collector command is killed"
+    )]
+    pub collector_command_timeout: u64,
+    #[arg(
+        long,
+        default_value_t = 4,
+        help = "number of collector commands allowed to run at the same time"
+    )]
+    pub max_concurrent_commands: usize,
+    #[arg(
+        long,
+        help = "directory where payloads the server did not accept are kept until they can be sent"
+    )]
+    pub spool_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        default_value_t = 256 * 1024 * 1024,
+        help = "size in bytes the spool may grow to before the oldest payloads are dropped"
+    )]
+    pub spool_max_bytes: u64,
+    #[arg(
+        long,
+        help = "address to serve prometheus metrics on, e.g. 127.0.0.1:9101. metrics are not served if unset"
+    )]
+    pub metrics_listen: Option<String>,
+}
+
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    let ratio: f64 = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))?;
+    if (0.0..=1.0).contains(&ratio) {
+        Ok(ratio)
+    } else {
+        Err(format!("`{value}` is not between 0.0 and 1.0"))
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Table,
+    Json,
 }
 #[derive(Subcommand, Debug)]
 pub(crate) enum Commands {
@@ -87,3 +203,18 @@ pub(crate) enum Commands {
     Server(ServerArgs),
     Scraper(ScraperArgs),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ratio() {
+        assert_eq!(parse_ratio("0.1"), Ok(0.1));
+        assert_eq!(parse_ratio("1"), Ok(1.0));
+        assert!(parse_ratio("5").is_err());
+        assert!(parse_ratio("-0.1").is_err());
+        assert!(parse_ratio("NaN").is_err());
+        assert!(parse_ratio("ten").is_err());
+    }
+}
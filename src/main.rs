@@ -16,7 +16,7 @@ fn setup_logging() -> EnvFilter {
 
     tracing_subscriber::registry()
         .with(filter.clone())
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(std::io::stderr))
         .init();
     filter
 }
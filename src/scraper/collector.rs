@@ -0,0 +1,151 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use serde::Deserialize;
+use tokio::{process::Command, sync::Semaphore};
+
+use crate::{scraper::scrape_error::CommandError, shared::dto::host::CurrentHostDto};
+
+/// How the activation log of a host is collected.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CollectorKind {
+    /// Fetch the csv from the activation logger over http.
+    #[default]
+    Http,
+    /// Run a command, e.g. over ssh, that prints the csv to stdout.
+    Command,
+}
+
+/// An entry of the hosts file.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ScrapeTarget {
+    #[serde(flatten)]
+    pub(crate) host: CurrentHostDto,
+    #[serde(default)]
+    pub(crate) collector: CollectorKind,
+    /// Overrides `--collector-command` for this host.
+    #[serde(default)]
+    pub(crate) collector_command: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CommandCollector {
+    default_command: String,
+    timeout: Duration,
+    permits: Arc<Semaphore>,
+}
+
+impl CommandCollector {
+    pub(crate) fn new(default_command: String, timeout: Duration, max_concurrent: usize) -> Self {
+        Self {
+            default_command,
+            timeout,
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    pub(crate) async fn collect(&self, target: &ScrapeTarget) -> Result<Vec<u8>, CommandError> {
+        let template = target
+            .collector_command
+            .as_deref()
+            .unwrap_or(&self.default_command);
+        let command = expand_command(template, &target.host);
+
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("command collector semaphore is never closed");
+        tracing::debug!(host = %target.host.hostname, command = %command, "running collector command");
+
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(CommandError::Spawn)?;
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| CommandError::Timeout(self.timeout))?
+            .map_err(CommandError::Spawn)?;
+
+        if !output.status.success() {
+            return Err(CommandError::Exit {
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(output.stdout)
+    }
+}
+
+fn expand_command(template: &str, host: &CurrentHostDto) -> String {
+    template
+        .replace("{host_url}", host.host_url.trim_end_matches('/'))
+        .replace("{hostname}", &host.hostname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scrape_targets() {
+        let content = r#"[
+            {"hostname": "a", "host_url": "a.example.com", "metadata": {"env": "prod"}},
+            {"hostname": "b", "host_url": "b.example.com", "metadata": {}, "collector": "command"},
+            {"hostname": "c", "host_url": "c.example.com", "metadata": {}, "collector": "command", "collector_command": "cat /tmp/{hostname}.csv"}
+        ]"#;
+        let targets: Vec<ScrapeTarget> = serde_json::from_str(content).unwrap();
+        assert_eq!(targets[0].collector, CollectorKind::Http);
+        assert_eq!(targets[0].host.metadata["env"], "prod");
+        assert_eq!(targets[1].collector, CollectorKind::Command);
+        assert_eq!(targets[1].collector_command, None);
+        assert_eq!(
+            targets[2].collector_command.as_deref(),
+            Some("cat /tmp/{hostname}.csv")
+        );
+    }
+
+    #[test]
+    fn test_expand_command() {
+        let host = CurrentHostDto {
+            hostname: "foo".to_string(),
+            host_url: "foo.example.com/".to_string(),
+            metadata: Default::default(),
+            logs: None,
+        };
+        assert_eq!(
+            expand_command("ssh {host_url} cat /var/log/{hostname}.csv", &host),
+            "ssh foo.example.com cat /var/log/foo.csv"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout() {
+        let collector = CommandCollector::new("sleep 5".to_string(), Duration::from_millis(50), 1);
+        let target: ScrapeTarget =
+            serde_json::from_str(r#"{"hostname": "a", "host_url": "a", "metadata": {}}"#).unwrap();
+        let err = collector.collect(&target).await.unwrap_err();
+        assert!(matches!(err, CommandError::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_command_failure() {
+        let collector = CommandCollector::new(
+            "echo nope >&2; exit 3".to_string(),
+            Duration::from_secs(5),
+            1,
+        );
+        let target: ScrapeTarget =
+            serde_json::from_str(r#"{"hostname": "a", "host_url": "a", "metadata": {}}"#).unwrap();
+        match collector.collect(&target).await.unwrap_err() {
+            CommandError::Exit { stderr, .. } => assert_eq!(stderr, "nope"),
+            err => panic!("unexpected error {err}"),
+        }
+    }
+}
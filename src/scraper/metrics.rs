@@ -0,0 +1,329 @@
+use std::{
+    sync::{Arc, atomic::AtomicU64},
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::IntoResponse,
+    routing::get,
+};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        gauge::Gauge,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+
+use crate::scraper::{HostScrapeStats, scrape_error::ScrapeError, spool::Spool};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct HostLabels {
+    host: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct FailureLabels {
+    host: String,
+    kind: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ResponseLabels {
+    endpoint: String,
+    code: String,
+}
+
+type HistogramFamily = Family<HostLabels, Histogram, fn() -> Histogram>;
+
+fn scrape_duration_histogram() -> Histogram {
+    // 50ms up to ~100s
+    Histogram::new(exponential_buckets(0.05, 2.0, 12))
+}
+
+#[derive(Debug)]
+pub(crate) struct ScraperMetrics {
+    registry: Registry,
+    scrape_duration: HistogramFamily,
+    scrape_success: Family<HostLabels, Counter>,
+    scrape_spooled: Family<HostLabels, Counter>,
+    scrape_failures: Family<FailureLabels, Counter>,
+    csv_parse_errors: Family<HostLabels, Gauge>,
+    records_posted: Family<HostLabels, Counter>,
+    server_responses: Family<ResponseLabels, Counter>,
+    fleet_pass_duration: Gauge<f64, AtomicU64>,
+    fleet_passes: Counter,
+    spool_pending_entries: Gauge,
+    spool_pending_bytes: Gauge,
+}
+
+impl ScraperMetrics {
+    pub(crate) fn new(spool: Option<&Spool>) -> Self {
+        let mut registry = Registry::with_prefix("hostmap_scraper");
+        let scrape_duration = HistogramFamily::new_with_constructor(scrape_duration_histogram);
+        registry.register_with_unit(
+            "host_scrape_duration",
+            "Time spent collecting and sending the activation log of a host",
+            Unit::Seconds,
+            scrape_duration.clone(),
+        );
+        let scrape_success = Family::<HostLabels, Counter>::default();
+        registry.register(
+            "host_scrape_success",
+            "Hosts scraped and sent to the server without errors",
+            scrape_success.clone(),
+        );
+        let scrape_spooled = Family::<HostLabels, Counter>::default();
+        registry.register(
+            "host_scrape_spooled",
+            "Hosts scraped and queued in the spool behind payloads the server has not taken yet",
+            scrape_spooled.clone(),
+        );
+        let scrape_failures = Family::<FailureLabels, Counter>::default();
+        registry.register(
+            "host_scrape_failures",
+            "Failed host scrapes by kind of error",
+            scrape_failures.clone(),
+        );
+        let csv_parse_errors = Family::<HostLabels, Gauge>::default();
+        registry.register(
+            "csv_parse_errors",
+            "Lines of the activation log that could not be parsed at the last scrape",
+            csv_parse_errors.clone(),
+        );
+        let records_posted = Family::<HostLabels, Counter>::default();
+        registry.register(
+            "records_posted",
+            "Activation records sent to the server",
+            records_posted.clone(),
+        );
+        let server_responses = Family::<ResponseLabels, Counter>::default();
+        registry.register(
+            "server_responses",
+            "Responses from the hostmap server by status code, `error` if there was none",
+            server_responses.clone(),
+        );
+        let fleet_pass_duration = Gauge::<f64, AtomicU64>::default();
+        registry.register_with_unit(
+            "fleet_pass_duration",
+            "Time the last pass over every host took",
+            Unit::Seconds,
+            fleet_pass_duration.clone(),
+        );
+        let fleet_passes = Counter::default();
+        registry.register(
+            "fleet_passes",
+            "Completed passes over every host",
+            fleet_passes.clone(),
+        );
+        let spool_pending_entries = Gauge::default();
+        let spool_pending_bytes = Gauge::default();
+        if let Some(spool) = spool {
+            registry.register(
+                "spool_pending_entries",
+                "Payloads waiting in the spool",
+                spool_pending_entries.clone(),
+            );
+            registry.register_with_unit(
+                "spool_pending",
+                "Size of the payloads waiting in the spool",
+                Unit::Bytes,
+                spool_pending_bytes.clone(),
+            );
+            let counters = spool.counters();
+            registry.register(
+                "spool_spooled",
+                "Payloads written to the spool",
+                counters.spooled,
+            );
+            registry.register(
+                "spool_replayed",
+                "Spooled payloads accepted by the server",
+                counters.replayed,
+            );
+            registry.register(
+                "spool_dropped",
+                "Spooled payloads dropped because the spool was full or the server rejected them",
+                counters.dropped,
+            );
+        }
+        Self {
+            registry,
+            scrape_duration,
+            scrape_success,
+            scrape_spooled,
+            scrape_failures,
+            csv_parse_errors,
+            records_posted,
+            server_responses,
+            fleet_pass_duration,
+            fleet_passes,
+            spool_pending_entries,
+            spool_pending_bytes,
+        }
+    }
+
+    pub(crate) fn observe_scrape(
+        &self,
+        host: &str,
+        duration: Duration,
+        result: Result<&HostScrapeStats, &ScrapeError>,
+    ) {
+        let labels = HostLabels {
+            host: host.to_string(),
+        };
+        self.scrape_duration
+            .get_or_create(&labels)
+            .observe(duration.as_secs_f64());
+        match result {
+            Ok(stats) if stats.spooled => {
+                self.scrape_spooled.get_or_create(&labels).inc();
+            }
+            Ok(_) => {
+                self.scrape_success.get_or_create(&labels).inc();
+            }
+            Err(err) => {
+                self.scrape_failures
+                    .get_or_create(&FailureLabels {
+                        host: labels.host,
+                        kind: err.kind().to_string(),
+                    })
+                    .inc();
+            }
+        }
+    }
+
+    pub(crate) fn observe_parse_errors(&self, host: &str, parse_errors: usize) {
+        self.csv_parse_errors
+            .get_or_create(&HostLabels {
+                host: host.to_string(),
+            })
+            .set(parse_errors as i64);
+    }
+
+    pub(crate) fn observe_records_posted(&self, host: &str, records: usize) {
+        self.records_posted
+            .get_or_create(&HostLabels {
+                host: host.to_string(),
+            })
+            .inc_by(records as u64);
+    }
+
+    pub(crate) fn observe_response(
+        &self,
+        endpoint: &str,
+        response: &Result<reqwest::Response, reqwest::Error>,
+    ) {
+        let code = match response {
+            Ok(res) => res.status().as_u16().to_string(),
+            Err(err) => err
+                .status()
+                .map(|s| s.as_u16().to_string())
+                .unwrap_or_else(|| "error".to_string()),
+        };
+        self.server_responses
+            .get_or_create(&ResponseLabels {
+                endpoint: endpoint.to_string(),
+                code,
+            })
+            .inc();
+    }
+
+    pub(crate) fn observe_fleet_pass(&self, duration: Duration) {
+        self.fleet_pass_duration.set(duration.as_secs_f64());
+        self.fleet_passes.inc();
+    }
+
+    fn encode(&self, spool: Option<&Spool>) -> Result<String, std::fmt::Error> {
+        if let Some(spool) = spool {
+            let stats = spool.stats();
+            self.spool_pending_entries.set(stats.pending_entries as i64);
+            self.spool_pending_bytes.set(stats.pending_bytes as i64);
+        }
+        let mut body = String::new();
+        encode(&mut body, &self.registry)?;
+        Ok(body)
+    }
+}
+
+#[derive(Clone)]
+struct MetricsState {
+    metrics: Arc<ScraperMetrics>,
+    spool: Option<Arc<Spool>>,
+}
+
+async fn serve_metrics(
+    State(MetricsState { metrics, spool }): State<MetricsState>,
+) -> impl IntoResponse {
+    match metrics.encode(spool.as_deref()) {
+        Ok(body) => (
+            StatusCode::OK,
+            [(
+                CONTENT_TYPE,
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Serves `/metrics` on `bind_addr` in the background.
+pub(crate) async fn spawn_metrics_listener(
+    bind_addr: &str,
+    metrics: Arc<ScraperMetrics>,
+    spool: Option<Arc<Spool>>,
+) {
+    let router = Router::new()
+        .route("/metrics", get(serve_metrics))
+        .with_state(MetricsState { metrics, spool });
+    let listener = tokio::net::TcpListener::bind(bind_addr)
+        .await
+        .unwrap_or_else(|_| {
+            panic!(
+                "Failed to bind to address {}, is the port already in use?",
+                bind_addr
+            )
+        });
+    tracing::info!("Serving scraper metrics at http://{}/metrics", bind_addr);
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, router.into_make_service()).await {
+            tracing::error!(error = %err, "metrics listener stopped");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_metrics() {
+        let metrics = ScraperMetrics::new(None);
+        let stats = |spooled| HostScrapeStats {
+            new_activations: None,
+            parse_errors: 0,
+            spooled,
+        };
+        metrics.observe_scrape("foo", Duration::from_millis(120), Ok(&stats(false)));
+        metrics.observe_scrape("bar", Duration::from_millis(120), Ok(&stats(true)));
+        metrics.observe_parse_errors("foo", 3);
+        metrics.observe_records_posted("foo", 10);
+        metrics.observe_fleet_pass(Duration::from_secs(2));
+        let body = metrics.encode(None).unwrap();
+        assert!(body.contains(r#"hostmap_scraper_host_scrape_success_total{host="foo"} 1"#));
+        assert!(!body.contains(r#"hostmap_scraper_host_scrape_success_total{host="bar"}"#));
+        assert!(body.contains(r#"hostmap_scraper_host_scrape_spooled_total{host="bar"} 1"#));
+        assert!(body.contains(r#"hostmap_scraper_csv_parse_errors{host="foo"} 3"#));
+        assert!(body.contains(r#"hostmap_scraper_records_posted_total{host="foo"} 10"#));
+        assert!(body.contains("hostmap_scraper_fleet_pass_duration_seconds 2.0"));
+        assert!(body.contains("hostmap_scraper_fleet_passes_total 1"));
+    }
+}
@@ -1,8 +1,26 @@
+mod collector;
+mod metrics;
+mod scrape_error;
+mod spool;
+mod summary;
+
 use futures::future::join_all;
-use std::{error, path::PathBuf, time::Duration};
+use std::{
+    error,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     cli::ScraperArgs,
+    scraper::{
+        collector::{CollectorKind, CommandCollector, ScrapeTarget},
+        metrics::{ScraperMetrics, spawn_metrics_listener},
+        scrape_error::ScrapeError,
+        spool::{Spool, SpoolEntry},
+        summary::ScrapeSummary,
+    },
     server::endpoint,
     shared::{
         dto::{
@@ -13,19 +31,49 @@ use crate::{
         model::activation::NewActivation,
     },
 };
-use reqwest::{Client, Url, header};
+use reqwest::{Client, StatusCode, Url, header};
 
-pub async fn scrape_hosts_batched(
-    hosts: &[CurrentHostDto],
-    client: &Client,
+#[derive(Debug)]
+pub(crate) struct HostScrapeReport {
+    pub(crate) hostname: String,
+    pub(crate) result: Result<HostScrapeStats, ScrapeError>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HostScrapeStats {
+    pub(crate) new_activations: Option<u64>,
+    pub(crate) parse_errors: usize,
+    /// Queued in the spool behind payloads the server has not taken yet, so
+    /// not delivered.
+    pub(crate) spooled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ActivationLog {
+    activations: Vec<NewActivation>,
+    parse_errors: usize,
+}
+
+/// Everything a scrape needs besides the hosts.
+#[derive(Debug, Clone)]
+struct ScrapeContext {
+    client: Client,
+    url: String,
+    command_collector: CommandCollector,
+    spool: Option<Arc<Spool>>,
+    metrics: Arc<ScraperMetrics>,
+}
+
+async fn scrape_hosts_batched(
+    targets: &[ScrapeTarget],
+    ctx: &ScrapeContext,
     ScraperArgs {
         scrape_interval,
         concurrent_requests,
         activation_logger_port,
-        url,
         ..
     }: &ScraperArgs,
-) -> Result<(), reqwest::Error> {
+) -> Vec<HostScrapeReport> {
     let interval = Duration::from_secs(*scrape_interval);
     let mut ticker = tokio::time::interval(interval);
     ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
@@ -33,46 +81,76 @@ pub async fn scrape_hosts_batched(
 
     tracing::debug!(
         "running scraper from start of list with {} hosts",
-        hosts.len()
+        targets.len()
     );
-    for (batch_idx, batch) in hosts.chunks(*concurrent_requests).enumerate() {
+    let pass_started = Instant::now();
+    let mut reports = Vec::with_capacity(targets.len());
+    for (batch_idx, batch) in targets.chunks(*concurrent_requests).enumerate() {
         ticker.tick().await;
 
-        let futs = batch.iter().map(|host| {
-            let client = client.clone();
-            let base_url = url.to_string();
+        let futs = batch.iter().map(|target| {
+            let host = &target.host;
             let hostname = host.hostname.clone();
             async move {
-                let res = async {
-                    let new_activations =
-                        scrape_host(host, &client, *activation_logger_port).await?;
-                    let res_text =
-                        insert_activations(host, new_activations, &client, &base_url).await?;
+                let started = Instant::now();
+                let result = async {
+                    let activation_log = scrape_host(target, ctx, *activation_logger_port).await?;
+                    ctx.metrics
+                        .observe_parse_errors(&host.hostname, activation_log.parse_errors);
+                    let body = activations_payload(host, activation_log.activations);
+                    if let Some(spool) = queued_spool(ctx) {
+                        spool_payload(spool, SpoolEntry::Activations(body));
+                        return Ok(HostScrapeStats {
+                            new_activations: None,
+                            parse_errors: activation_log.parse_errors,
+                            spooled: true,
+                        });
+                    }
+                    let res_text = match post_activations(&body, ctx).await {
+                        Ok(res_text) => res_text,
+                        Err(err) => {
+                            if let Some(spool) = &ctx.spool
+                                && !rejected_by_server(&err)
+                            {
+                                spool_payload(spool, SpoolEntry::Activations(body));
+                            }
+                            return Err(ScrapeError::Post(err));
+                        }
+                    };
                     tracing::debug!(response_text=%res_text, request_host=%host.hostname);
-                    Ok::<(), reqwest::Error>(())
+                    Ok::<HostScrapeStats, ScrapeError>(HostScrapeStats {
+                        new_activations: parse_created_count(&res_text),
+                        parse_errors: activation_log.parse_errors,
+                        spooled: false,
+                    })
                 }
                 .await;
-                (hostname, res)
+                ctx.metrics
+                    .observe_scrape(&hostname, started.elapsed(), result.as_ref());
+                HostScrapeReport { hostname, result }
             }
         });
 
         let results = join_all(futs).await;
         let mut ok = 0;
+        let mut spooled = 0;
         let mut fail = 0;
 
-        for (host_id, res) in results {
-            match res {
+        for report in &results {
+            match &report.result {
+                Ok(stats) if stats.spooled => spooled += 1,
                 Ok(_) => ok += 1,
                 Err(e) => {
                     fail += 1;
-                    tracing::debug!(host = %host_id, error = %e, "scrape attempt failed, skipping host");
+                    tracing::debug!(host = %report.hostname, error = %e, "scrape attempt failed, skipping host");
                 }
             }
         }
-        if fail > 0 {
+        if fail > 0 || spooled > 0 {
             tracing::info!(
                 batch_idx = batch_idx,
                 ok = ok,
+                spooled = spooled,
                 fail = fail,
                 "completed scraping batch"
             );
@@ -80,13 +158,16 @@ pub async fn scrape_hosts_batched(
             tracing::debug!(
                 batch_idx = batch_idx,
                 ok = ok,
+                spooled = spooled,
                 fail = fail,
                 "completed scraping batch"
             );
         }
+        reports.extend(results);
     }
+    ctx.metrics.observe_fleet_pass(pass_started.elapsed());
 
-    Ok(())
+    reports
 }
 
 pub async fn run(
@@ -99,7 +180,8 @@ pub async fn run(
         scraper_args.scrape_interval,
         scraper_args.concurrent_requests,
     );
-    let create_host_dtos = parse_hosts(&scraper_args.hosts_file);
+    let targets = parse_hosts(&scraper_args.hosts_file);
+    let create_host_dtos: Vec<CurrentHostDto> = targets.iter().map(|t| t.host.clone()).collect();
     let mut headers = header::HeaderMap::new();
     headers.insert(
         header::AUTHORIZATION,
@@ -109,7 +191,27 @@ pub async fn run(
         .connect_timeout(std::time::Duration::from_secs(10))
         .default_headers(headers)
         .build()?;
-    insert_hosts(&create_host_dtos, &client, &scraper_args.url).await?;
+    let spool = scraper_args.spool_dir.clone().map(|dir| {
+        Arc::new(
+            Spool::open(dir.clone(), scraper_args.spool_max_bytes)
+                .unwrap_or_else(|err| panic!("could not open spool directory {dir:?}: {err}")),
+        )
+    });
+    let metrics = Arc::new(ScraperMetrics::new(spool.as_deref()));
+    if let Some(bind_addr) = &scraper_args.metrics_listen {
+        spawn_metrics_listener(bind_addr, metrics.clone(), spool.clone()).await;
+    }
+    let ctx = ScrapeContext {
+        client,
+        url: scraper_args.url.clone(),
+        command_collector: CommandCollector::new(
+            scraper_args.collector_command.clone(),
+            Duration::from_secs(scraper_args.collector_command_timeout),
+            scraper_args.max_concurrent_commands,
+        ),
+        spool,
+        metrics,
+    };
     let total_hosts = create_host_dtos.len();
     let batches = total_hosts.div_ceil(scraper_args.concurrent_requests);
 
@@ -120,35 +222,174 @@ pub async fn run(
         "configured scraper batching"
     );
 
+    if scraper_args.once {
+        if let Some(spool) = &ctx.spool {
+            replay_spool(spool, &ctx).await;
+        }
+        if !register_hosts(&create_host_dtos, &ctx).await {
+            return Err("could not register hosts with the server".into());
+        }
+        return scrape_once(&targets, &ctx, &scraper_args).await;
+    }
+
+    let mut hosts_registered = false;
     loop {
-        scrape_hosts_batched(&create_host_dtos, &client, &scraper_args).await?;
+        if let Some(spool) = &ctx.spool {
+            replay_spool(spool, &ctx).await;
+        }
+        if !hosts_registered {
+            hosts_registered = register_hosts(&create_host_dtos, &ctx).await;
+        }
+        scrape_hosts_batched(&targets, &ctx, &scraper_args).await;
+    }
+}
+
+/// Sends the hosts to the server, or queues them in the spool. Returns false
+/// if it should be tried again on the next pass.
+async fn register_hosts(host_dtos: &[CurrentHostDto], ctx: &ScrapeContext) -> bool {
+    if let Some(spool) = queued_spool(ctx) {
+        tracing::warn!(
+            "server has not taken the spooled payloads yet, spooling the hosts behind them"
+        );
+        spool_payload(spool, SpoolEntry::Hosts(host_dtos.to_vec()));
+        return true;
+    }
+    match insert_hosts(host_dtos, ctx).await {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::error!(error = %err, "could not register hosts with the server");
+            match &ctx.spool {
+                Some(spool) if !rejected_by_server(&err) => {
+                    spool_payload(spool, SpoolEntry::Hosts(host_dtos.to_vec()));
+                    true
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+/// The spool if it still holds payloads the server has not taken. New payloads
+/// go behind them instead of to the server, so they arrive in order.
+fn queued_spool(ctx: &ScrapeContext) -> Option<&Spool> {
+    ctx.spool.as_deref().filter(|spool| spool.has_pending())
+}
+
+fn spool_payload(spool: &Spool, entry: SpoolEntry) {
+    if let Err(err) = spool.push(entry) {
+        tracing::error!(error = %err, "could not write payload to spool, it is lost");
+    }
+}
+
+/// Sends spooled payloads oldest first and stops at the first one the server
+/// does not take, so they keep their order until it is reachable again.
+async fn replay_spool(spool: &Spool, ctx: &ScrapeContext) {
+    let pending = match spool.pending() {
+        Ok(pending) => pending,
+        Err(err) => {
+            tracing::error!(error = %err, "could not read spool");
+            return;
+        }
+    };
+    if pending.is_empty() {
+        return;
+    }
+
+    let mut replayed = 0;
+    for spooled in &pending {
+        let res = match &spooled.entry {
+            SpoolEntry::Hosts(hosts) => insert_hosts(hosts, ctx).await,
+            SpoolEntry::Activations(body) => post_activations(body, ctx).await.map(|_| ()),
+        };
+        let res = match res {
+            Ok(()) => spool.mark_replayed(spooled),
+            Err(err) if rejected_by_server(&err) => {
+                tracing::warn!(error = %err, "server rejected spooled payload, dropping it");
+                spool.mark_rejected(spooled)
+            }
+            Err(err) => {
+                tracing::debug!(error = %err, "server still unavailable, keeping spool");
+                break;
+            }
+        };
+        if let Err(err) = res {
+            tracing::error!(error = %err, "could not remove entry from spool");
+            break;
+        }
+        replayed += 1;
     }
+
+    let stats = spool.stats();
+    tracing::info!(
+        replayed = replayed,
+        pending_entries = stats.pending_entries,
+        pending_bytes = stats.pending_bytes,
+        dropped_total = stats.dropped_total,
+        "replayed spool"
+    );
+}
+
+/// Errors that mean the payload itself is bad, retrying it will not help.
+fn rejected_by_server(err: &reqwest::Error) -> bool {
+    matches!(
+        err.status(),
+        Some(
+            StatusCode::BAD_REQUEST
+                | StatusCode::PAYLOAD_TOO_LARGE
+                | StatusCode::UNSUPPORTED_MEDIA_TYPE
+                | StatusCode::UNPROCESSABLE_ENTITY
+        )
+    )
+}
+
+async fn scrape_once(
+    targets: &[ScrapeTarget],
+    ctx: &ScrapeContext,
+    scraper_args: &ScraperArgs,
+) -> Result<(), Box<dyn error::Error + Send + Sync + 'static>> {
+    let reports = scrape_hosts_batched(targets, ctx, scraper_args).await;
+    let summary = ScrapeSummary::new(reports);
+    println!("{}", summary.render(scraper_args.summary_format));
+
+    if summary.failure_ratio() > scraper_args.max_failure_ratio {
+        return Err(format!(
+            "failure ratio {:.2} is above the allowed {:.2}",
+            summary.failure_ratio(),
+            scraper_args.max_failure_ratio
+        )
+        .into());
+    }
+    Ok(())
 }
 
 fn read_hosts_from_file(path: &PathBuf) -> String {
     std::fs::read_to_string(path).expect("could not read target list file")
 }
 
-fn parse_hosts(host_file: &PathBuf) -> Vec<CurrentHostDto> {
+fn parse_hosts(host_file: &PathBuf) -> Vec<ScrapeTarget> {
     let content = read_hosts_from_file(host_file);
-    let host_dtos: Vec<CurrentHostDto> =
+    let targets: Vec<ScrapeTarget> =
         serde_json::from_str(&content).expect("could not parse target list file as json. the metadata field must be a key-value pair. nested json is not supported");
 
-    host_dtos
+    targets
 }
 
 async fn fetch_activationlog(
     url: &Url,
     client: &Client,
-) -> Result<Vec<ActivationDto>, reqwest::Error> {
+) -> Result<(Vec<ActivationDto>, usize), reqwest::Error> {
     let url = url.as_str();
     let res = client.get(url).send().await?;
     let body = res.text().await?;
 
+    Ok(parse_activationlog(body.as_bytes(), url))
+}
+
+fn parse_activationlog(body: &[u8], source: &str) -> (Vec<ActivationDto>, usize) {
     let mut rdr = csv::ReaderBuilder::new()
         .delimiter(b';')
         .has_headers(false)
-        .from_reader(body.as_bytes());
+        .from_reader(body);
 
     let mut log_records = Vec::new();
     let mut parse_errors = 0;
@@ -159,7 +400,7 @@ async fn fetch_activationlog(
                 parse_errors += 1;
                 if parse_errors <= 5 {
                     tracing::debug!(
-                        url = %url,
+                        source = %source,
                         line_idx = i,
                         error = %err,
                         "failed to parse csv line; skipping"
@@ -170,67 +411,201 @@ async fn fetch_activationlog(
         };
         log_records.push(line);
     }
-    Ok(log_records)
+    if parse_errors > 0 {
+        tracing::debug!(source = %source, parse_errors, "skipped unparsable csv lines");
+    }
+    (log_records, parse_errors)
+}
+
+/// The server answers a bulk activation insert with "<n> log entries created".
+fn parse_created_count(response_text: &str) -> Option<u64> {
+    response_text.split_whitespace().next()?.parse().ok()
 }
 
-pub(crate) async fn insert_hosts(
+async fn insert_hosts(
     host_dtos: &[CurrentHostDto],
-    client: &Client,
-    url: &str,
+    ctx: &ScrapeContext,
 ) -> Result<(), reqwest::Error> {
-    let url = format!("{}{}", url, endpoint::hosts_bulk());
-    client
-        .post(url)
-        .json(&host_dtos)
-        .send()
-        .await?
-        .error_for_status()?;
+    let url = format!("{}{}", ctx.url, endpoint::hosts_bulk());
+    let res = ctx.client.post(url).json(&host_dtos).send().await;
+    ctx.metrics.observe_response(endpoint::hosts_bulk(), &res);
+    res?.error_for_status()?;
     Ok(())
 }
 
-async fn insert_activations(
+fn activations_payload(
     host: &CurrentHostDto,
     activation_models: Vec<NewActivation>,
-    client: &Client,
-    url: &str,
-) -> Result<String, reqwest::Error> {
+) -> HostWithLogsDto {
     let activation_dtos: Vec<ActivationDto> = activation_models
         .into_iter()
         .map(ActivationDto::from)
         .collect();
-    let body = HostWithLogsDto {
+    HostWithLogsDto {
         hostname: host.hostname.clone(),
         host_url: host.host_url.clone(),
         logs: activation_dtos,
         metadata: host.metadata.clone(),
-    };
+    }
+}
 
-    let url = format!("{}{}", url, endpoint::activations_bulk());
-    let res = client.post(url).json(&body).send().await?;
+async fn post_activations(
+    body: &HostWithLogsDto,
+    ctx: &ScrapeContext,
+) -> Result<String, reqwest::Error> {
+    let url = format!("{}{}", ctx.url, endpoint::activations_bulk());
+    let res = ctx.client.post(url).json(body).send().await;
+    ctx.metrics
+        .observe_response(endpoint::activations_bulk(), &res);
+    let res = res?;
     res.error_for_status_ref()?;
     let res_text = res.text().await?;
+    ctx.metrics
+        .observe_records_posted(&body.hostname, body.logs.len());
     Ok(res_text)
 }
 
 async fn scrape_host(
-    host: &CurrentHostDto,
-    client: &Client,
+    target: &ScrapeTarget,
+    ctx: &ScrapeContext,
     activation_logger_port: usize,
-) -> Result<Vec<NewActivation>, reqwest::Error> {
-    let url_text = format!(
-        "http://{}:{}/hostmap/hostmap-activation-logs.csv",
-        host.host_url.trim_end_matches('/'),
-        activation_logger_port,
-    )
-    .to_owned();
+) -> Result<ActivationLog, ScrapeError> {
+    let host = &target.host;
+    let (recs, parse_errors) = match target.collector {
+        CollectorKind::Http => {
+            let url_text = format!(
+                "http://{}:{}/hostmap/hostmap-activation-logs.csv",
+                host.host_url.trim_end_matches('/'),
+                activation_logger_port,
+            )
+            .to_owned();
 
-    tracing::debug!("scraping url: {}", url_text);
-    let url = Url::parse(&url_text).expect("could not parse url");
-    let recs = fetch_activationlog(&url, client).await?;
-    let activation_models = recs
+            tracing::debug!("scraping url: {}", url_text);
+            let url = Url::parse(&url_text).expect("could not parse url");
+            fetch_activationlog(&url, &ctx.client)
+                .await
+                .map_err(ScrapeError::Fetch)?
+        }
+        CollectorKind::Command => {
+            let stdout = ctx.command_collector.collect(target).await?;
+            parse_activationlog(&stdout, &host.hostname)
+        }
+    };
+    let activations = recs
         .into_iter()
         .map(|dto| NewActivation::from((host, dto)))
         .collect::<Vec<NewActivation>>();
 
-    Ok(activation_models)
+    Ok(ActivationLog {
+        activations,
+        parse_errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SummaryFormat;
+    use std::collections::HashMap;
+
+    fn command_target(hostname: &str) -> ScrapeTarget {
+        ScrapeTarget {
+            host: CurrentHostDto {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::new(),
+                logs: None,
+            },
+            collector: CollectorKind::Command,
+            collector_command: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_delivers_nothing() {
+        let spool_dir =
+            std::env::temp_dir().join(format!("hostmap-scraper-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&spool_dir);
+        let args = ScraperArgs {
+            hosts_file: PathBuf::new(),
+            scrape_interval: 1,
+            concurrent_requests: 2,
+            activation_logger_port: 0,
+            api_key_file: PathBuf::new(),
+            // nothing listens on port 1
+            url: "http://127.0.0.1:1".to_string(),
+            once: true,
+            summary_format: SummaryFormat::Json,
+            max_failure_ratio: 0.5,
+            collector_command: "echo '2025-10-14 13:53:19+02:00;root;/nix/store/abc-nixos-system-{hostname};switch'".to_string(),
+            collector_command_timeout: 10,
+            max_concurrent_commands: 2,
+            spool_dir: Some(spool_dir.clone()),
+            spool_max_bytes: 1024 * 1024,
+            metrics_listen: None,
+        };
+        let spool = Arc::new(Spool::open(spool_dir.clone(), args.spool_max_bytes).unwrap());
+        let ctx = ScrapeContext {
+            client: Client::new(),
+            url: args.url.clone(),
+            command_collector: CommandCollector::new(
+                args.collector_command.clone(),
+                Duration::from_secs(args.collector_command_timeout),
+                args.max_concurrent_commands,
+            ),
+            spool: Some(spool.clone()),
+            metrics: Arc::new(ScraperMetrics::new(Some(&spool))),
+        };
+        let targets: Vec<ScrapeTarget> = ["a", "b", "c", "d", "e", "f"]
+            .into_iter()
+            .map(command_target)
+            .collect();
+
+        let reports = scrape_hosts_batched(&targets, &ctx, &args).await;
+        let outcomes: Vec<&str> = reports
+            .iter()
+            .map(|report| match &report.result {
+                Ok(stats) if stats.spooled => "spooled",
+                Ok(_) => "ok",
+                Err(err) => err.kind(),
+            })
+            .collect();
+        // the first batch fails to post, or queues behind a host of it that
+        // already failed, the later batches queue behind it
+        assert!(
+            outcomes[0] == "post" || outcomes[1] == "post",
+            "{outcomes:?}"
+        );
+        assert!(
+            outcomes[..2]
+                .iter()
+                .all(|o| *o == "post" || *o == "spooled"),
+            "{outcomes:?}"
+        );
+        assert_eq!(outcomes[2..], ["spooled"; 4]);
+        assert_eq!(spool.pending().unwrap().len(), 6);
+        assert!(scrape_once(&targets, &ctx, &args).await.is_err());
+        let _ = std::fs::remove_dir_all(&spool_dir);
+    }
+
+    #[test]
+    fn test_parse_activationlog_skips_bad_lines() {
+        let body =
+            b"2025-10-14 13:53:19+02:00;alice;/nix/store/abc-nixos-system-foo-25.05pre-git;switch
+not a valid line
+2025-10-14 14:00:00+02:00;root;/nix/store/def-nixos-system-foo-25.05pre-git;boot
+";
+        let (records, parse_errors) = parse_activationlog(body, "test");
+        assert_eq!(records.len(), 2);
+        assert_eq!(parse_errors, 1);
+        assert_eq!(records[0].username, "alice");
+        assert_eq!(records[1].activation_type, "boot");
+    }
+
+    #[test]
+    fn test_parse_created_count() {
+        assert_eq!(parse_created_count("3 log entries created"), Some(3));
+        assert_eq!(parse_created_count("created"), None);
+        assert_eq!(parse_created_count(""), None);
+    }
 }
@@ -0,0 +1,32 @@
+use std::{process::ExitStatus, time::Duration};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ScrapeError {
+    #[error("could not fetch activation log: {0}")]
+    Fetch(reqwest::Error),
+    #[error("could not collect activation log with command: {0}")]
+    Command(#[from] CommandError),
+    #[error("could not send activations to server: {0}")]
+    Post(reqwest::Error),
+}
+
+impl ScrapeError {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            ScrapeError::Fetch(_) => "fetch",
+            ScrapeError::Command(CommandError::Timeout(_)) => "command_timeout",
+            ScrapeError::Command(_) => "command",
+            ScrapeError::Post(_) => "post",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CommandError {
+    #[error("could not run command: {0}")]
+    Spawn(std::io::Error),
+    #[error("command timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("command exited with {status}: {stderr}")]
+    Exit { status: ExitStatus, stderr: String },
+}
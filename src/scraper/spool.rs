@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use prometheus_client::metrics::counter::Counter;
+use serde::{Deserialize, Serialize};
+
+use crate::shared::dto::{
+    activation::ActivationDto,
+    host::{CurrentHostDto, HostWithLogsDto},
+};
+
+/// A request to the server that could not be delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub(crate) enum SpoolEntry {
+    Hosts(Vec<CurrentHostDto>),
+    Activations(HostWithLogsDto),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SpooledEntry {
+    path: PathBuf,
+    pub(crate) entry: SpoolEntry,
+}
+
+/// Shared with the scraper metrics.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpoolCounters {
+    pub(crate) spooled: Counter,
+    pub(crate) replayed: Counter,
+    pub(crate) dropped: Counter,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SpoolStats {
+    pub(crate) pending_entries: u64,
+    pub(crate) pending_bytes: u64,
+    pub(crate) spooled_total: u64,
+    pub(crate) replayed_total: u64,
+    pub(crate) dropped_total: u64,
+}
+
+/// Same columns as the unique constraint on the activation table, minus hostname.
+type ActivationKey = (DateTime<Utc>, String, String, String);
+
+fn activation_key(dto: &ActivationDto) -> ActivationKey {
+    (
+        dto.activated_at,
+        dto.username.clone(),
+        dto.store_path.clone(),
+        dto.activation_type.clone(),
+    )
+}
+
+#[derive(Debug, Default)]
+struct SpoolState {
+    next_seq: u64,
+    /// Activations already waiting in the spool, so a host that keeps failing
+    /// does not get its whole log written again on every pass.
+    spooled: HashMap<String, HashSet<ActivationKey>>,
+}
+
+/// Directory of payloads waiting to be sent to the server, one json file per
+/// entry, named after a sequence number so they can be replayed in order.
+#[derive(Debug)]
+pub(crate) struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: Mutex<SpoolState>,
+    counters: SpoolCounters,
+}
+
+impl Spool {
+    pub(crate) fn open(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let spool = Self {
+            dir,
+            max_bytes,
+            state: Mutex::new(SpoolState::default()),
+            counters: SpoolCounters::default(),
+        };
+        let mut state = SpoolState::default();
+        for (seq, path) in spool.entry_paths()? {
+            state.next_seq = state.next_seq.max(seq + 1);
+            match read_entry(&path) {
+                Ok(SpoolEntry::Activations(body)) => {
+                    let keys = state.spooled.entry(body.hostname.clone()).or_default();
+                    keys.extend(body.logs.iter().map(activation_key));
+                }
+                Ok(SpoolEntry::Hosts(_)) => {}
+                Err(err) => {
+                    tracing::warn!(path = ?path, error = %err, "dropping unreadable spool entry");
+                    fs::remove_file(&path)?;
+                    spool.counters.dropped.inc();
+                }
+            }
+        }
+        *spool.state.lock().unwrap() = state;
+        Ok(spool)
+    }
+
+    /// Writes the entry to disk. Activations that are already spooled for the
+    /// same host are left out, and nothing is written if none are left.
+    pub(crate) fn push(&self, entry: SpoolEntry) -> io::Result<bool> {
+        let path = {
+            let mut state = self.state.lock().unwrap();
+            let entry = match entry {
+                SpoolEntry::Activations(mut body) => {
+                    let keys = state.spooled.entry(body.hostname.clone()).or_default();
+                    body.logs.retain(|dto| keys.insert(activation_key(dto)));
+                    if body.logs.is_empty() {
+                        return Ok(false);
+                    }
+                    SpoolEntry::Activations(body)
+                }
+                hosts => hosts,
+            };
+            let path = self.dir.join(format!("{:020}.json", state.next_seq));
+            state.next_seq += 1;
+            let tmp_path = path.with_extension("json.tmp");
+            fs::write(
+                &tmp_path,
+                serde_json::to_vec(&entry).expect("spool entries are always serializable"),
+            )?;
+            fs::rename(&tmp_path, &path)?;
+            path
+        };
+        self.counters.spooled.inc();
+        tracing::debug!(path = ?path, "spooled payload");
+        self.enforce_size_cap()?;
+        Ok(true)
+    }
+
+    /// All entries waiting to be replayed, oldest first.
+    pub(crate) fn pending(&self) -> io::Result<Vec<SpooledEntry>> {
+        let mut entries = Vec::new();
+        for (_, path) in self.entry_paths()? {
+            match read_entry(&path) {
+                Ok(entry) => entries.push(SpooledEntry { path, entry }),
+                Err(err) => {
+                    tracing::warn!(path = ?path, error = %err, "dropping unreadable spool entry");
+                    self.remove(&path)?;
+                    self.counters.dropped.inc();
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Whether payloads are waiting, new ones must then queue behind them to
+    /// reach the server in order.
+    pub(crate) fn has_pending(&self) -> bool {
+        self.entry_paths()
+            .map(|paths| !paths.is_empty())
+            .unwrap_or(true)
+    }
+
+    /// Removes an entry after the server accepted it.
+    pub(crate) fn mark_replayed(&self, spooled: &SpooledEntry) -> io::Result<()> {
+        self.forget(&spooled.entry);
+        self.remove(&spooled.path)?;
+        self.counters.replayed.inc();
+        Ok(())
+    }
+
+    /// Removes an entry the server will never accept.
+    pub(crate) fn mark_rejected(&self, spooled: &SpooledEntry) -> io::Result<()> {
+        self.forget(&spooled.entry);
+        self.remove(&spooled.path)?;
+        self.counters.dropped.inc();
+        Ok(())
+    }
+
+    pub(crate) fn stats(&self) -> SpoolStats {
+        let (pending_entries, pending_bytes) = self.entry_paths().unwrap_or_default().iter().fold(
+            (0, 0),
+            |(entries, bytes), (_, path)| {
+                let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                (entries + 1, bytes + len)
+            },
+        );
+        SpoolStats {
+            pending_entries,
+            pending_bytes,
+            spooled_total: self.counters.spooled.get(),
+            replayed_total: self.counters.replayed.get(),
+            dropped_total: self.counters.dropped.get(),
+        }
+    }
+
+    pub(crate) fn counters(&self) -> SpoolCounters {
+        self.counters.clone()
+    }
+
+    fn enforce_size_cap(&self) -> io::Result<()> {
+        let mut sized = Vec::new();
+        let mut total = 0;
+        for (_, path) in self.entry_paths()? {
+            let len = fs::metadata(&path)?.len();
+            total += len;
+            sized.push((path, len));
+        }
+        for (path, len) in sized {
+            if total <= self.max_bytes {
+                break;
+            }
+            if let Ok(entry) = read_entry(&path) {
+                self.forget(&entry);
+            }
+            self.remove(&path)?;
+            total -= len;
+            self.counters.dropped.inc();
+            tracing::warn!(path = ?path, max_bytes = self.max_bytes, "spool is full, dropped oldest entry");
+        }
+        Ok(())
+    }
+
+    fn forget(&self, entry: &SpoolEntry) {
+        if let SpoolEntry::Activations(body) = entry {
+            let mut state = self.state.lock().unwrap();
+            if let Some(keys) = state.spooled.get_mut(&body.hostname) {
+                for dto in &body.logs {
+                    keys.remove(&activation_key(dto));
+                }
+            }
+        }
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry_paths(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+        let mut paths = Vec::new();
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json")
+                && let Some(seq) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                paths.push((seq, path));
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+fn read_entry(path: &Path) -> io::Result<SpoolEntry> {
+    let content = fs::read(path)?;
+    serde_json::from_slice(&content).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hostmap-spool-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn activation(minute: u32) -> ActivationDto {
+        ActivationDto {
+            activated_at: format!("2025-10-14T13:{minute:02}:00Z").parse().unwrap(),
+            username: "root".to_string(),
+            store_path: format!("/nix/store/{minute}"),
+            activation_type: "switch".to_string(),
+            revision: None,
+        }
+    }
+
+    fn activations(hostname: &str, minutes: &[u32]) -> SpoolEntry {
+        SpoolEntry::Activations(HostWithLogsDto {
+            hostname: hostname.to_string(),
+            host_url: hostname.to_string(),
+            metadata: HashMap::new(),
+            logs: minutes.iter().map(|m| activation(*m)).collect(),
+        })
+    }
+
+    fn logs_of(entry: &SpooledEntry) -> Vec<String> {
+        match &entry.entry {
+            SpoolEntry::Activations(body) => {
+                body.logs.iter().map(|l| l.store_path.clone()).collect()
+            }
+            SpoolEntry::Hosts(_) => vec!["hosts".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_entries_are_replayed_in_order() {
+        let spool = Spool::open(test_dir("order"), u64::MAX).unwrap();
+        spool.push(SpoolEntry::Hosts(vec![])).unwrap();
+        spool.push(activations("a", &[1])).unwrap();
+        spool.push(activations("b", &[2])).unwrap();
+        let pending = spool.pending().unwrap();
+        let logs: Vec<Vec<String>> = pending.iter().map(logs_of).collect();
+        assert_eq!(
+            logs,
+            vec![
+                vec!["hosts".to_string()],
+                vec!["/nix/store/1".to_string()],
+                vec!["/nix/store/2".to_string()]
+            ]
+        );
+
+        spool.mark_replayed(&pending[0]).unwrap();
+        let stats = spool.stats();
+        assert_eq!(stats.pending_entries, 2);
+        assert_eq!(stats.replayed_total, 1);
+
+        assert!(spool.has_pending());
+        for spooled in &pending[1..] {
+            spool.mark_replayed(spooled).unwrap();
+        }
+        assert!(!spool.has_pending());
+    }
+
+    #[test]
+    fn test_already_spooled_activations_are_skipped() {
+        let spool = Spool::open(test_dir("dedup"), u64::MAX).unwrap();
+        assert!(spool.push(activations("a", &[1, 2])).unwrap());
+        assert!(!spool.push(activations("a", &[1, 2])).unwrap());
+        assert!(spool.push(activations("a", &[1, 2, 3])).unwrap());
+        assert!(spool.push(activations("b", &[1])).unwrap());
+        let pending = spool.pending().unwrap();
+        assert_eq!(pending.len(), 3);
+        assert_eq!(logs_of(&pending[1]), vec!["/nix/store/3".to_string()]);
+
+        // once delivered, the same activations can be spooled again
+        spool.mark_replayed(&pending[0]).unwrap();
+        assert!(spool.push(activations("a", &[1])).unwrap());
+    }
+
+    #[test]
+    fn test_size_cap_drops_oldest() {
+        let dir = test_dir("cap");
+        let spool = Spool::open(dir.clone(), u64::MAX).unwrap();
+        spool.push(activations("a", &[1])).unwrap();
+        let entry_size = spool.stats().pending_bytes;
+
+        let spool = Spool::open(dir, entry_size * 2).unwrap();
+        spool.push(activations("a", &[2])).unwrap();
+        spool.push(activations("a", &[3])).unwrap();
+        let pending = spool.pending().unwrap();
+        let logs: Vec<Vec<String>> = pending.iter().map(logs_of).collect();
+        assert_eq!(
+            logs,
+            vec![
+                vec!["/nix/store/2".to_string()],
+                vec!["/nix/store/3".to_string()]
+            ]
+        );
+        assert_eq!(spool.stats().dropped_total, 1);
+    }
+
+    #[test]
+    fn test_reopen_continues_sequence() {
+        let dir = test_dir("reopen");
+        let spool = Spool::open(dir.clone(), u64::MAX).unwrap();
+        spool.push(activations("a", &[1])).unwrap();
+        drop(spool);
+
+        let spool = Spool::open(dir, u64::MAX).unwrap();
+        assert!(!spool.push(activations("a", &[1])).unwrap());
+        spool.push(activations("a", &[2])).unwrap();
+        let pending = spool.pending().unwrap();
+        assert_eq!(logs_of(&pending[1]), vec!["/nix/store/2".to_string()]);
+    }
+}
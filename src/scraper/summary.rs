@@ -0,0 +1,209 @@
+use serde::Serialize;
+
+use crate::{cli::SummaryFormat, scraper::HostScrapeReport};
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HostSummary {
+    hostname: String,
+    /// Delivered to the server.
+    ok: bool,
+    spooled: bool,
+    new_activations: Option<u64>,
+    parse_errors: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<HostScrapeReport> for HostSummary {
+    fn from(report: HostScrapeReport) -> Self {
+        match report.result {
+            Ok(stats) => Self {
+                hostname: report.hostname,
+                ok: !stats.spooled,
+                spooled: stats.spooled,
+                new_activations: stats.new_activations,
+                parse_errors: stats.parse_errors,
+                error_kind: None,
+                error: None,
+            },
+            Err(err) => Self {
+                hostname: report.hostname,
+                ok: false,
+                spooled: false,
+                new_activations: None,
+                parse_errors: 0,
+                error_kind: Some(err.kind()),
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+/// Spooled hosts are not delivered yet, they count towards the failure ratio.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ScrapeSummary {
+    hosts: Vec<HostSummary>,
+    total: usize,
+    ok: usize,
+    spooled: usize,
+    failed: usize,
+    failure_ratio: f64,
+}
+
+impl ScrapeSummary {
+    pub(crate) fn new(reports: Vec<HostScrapeReport>) -> Self {
+        let hosts: Vec<HostSummary> = reports.into_iter().map(HostSummary::from).collect();
+        let total = hosts.len();
+        let ok = hosts.iter().filter(|h| h.ok).count();
+        let spooled = hosts.iter().filter(|h| h.spooled).count();
+        let failed = total - ok - spooled;
+        let failure_ratio = if total == 0 {
+            0.0
+        } else {
+            (failed + spooled) as f64 / total as f64
+        };
+        Self {
+            hosts,
+            total,
+            ok,
+            spooled,
+            failed,
+            failure_ratio,
+        }
+    }
+
+    pub(crate) fn failure_ratio(&self) -> f64 {
+        self.failure_ratio
+    }
+
+    pub(crate) fn render(&self, format: SummaryFormat) -> String {
+        match format {
+            SummaryFormat::Json => {
+                serde_json::to_string_pretty(self).expect("summary is always serializable")
+            }
+            SummaryFormat::Table => self.render_table(),
+        }
+    }
+
+    fn render_table(&self) -> String {
+        let width = self
+            .hosts
+            .iter()
+            .map(|h| h.hostname.len())
+            .max()
+            .unwrap_or(0)
+            .max("HOST".len());
+        let mut out = format!(
+            "{:<width$}  {:<7}  {:>15}  {:>12}  ERROR\n",
+            "HOST", "STATUS", "NEW_ACTIVATIONS", "PARSE_ERRORS"
+        );
+        for host in &self.hosts {
+            let status = match (host.ok, host.spooled) {
+                (true, _) => "ok",
+                (false, true) => "spooled",
+                (false, false) => "failed",
+            };
+            let new_activations = host
+                .new_activations
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            out.push_str(&format!(
+                "{:<width$}  {:<7}  {:>15}  {:>12}  {}\n",
+                host.hostname,
+                status,
+                new_activations,
+                host.parse_errors,
+                host.error.as_deref().unwrap_or("")
+            ));
+        }
+        out.push_str(&format!(
+            "\n{} hosts, {} ok, {} spooled, {} failed (failure ratio {:.2})",
+            self.total, self.ok, self.spooled, self.failed, self.failure_ratio
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraper::{HostScrapeStats, scrape_error::ScrapeError};
+
+    fn report(hostname: &str, ok: bool) -> HostScrapeReport {
+        HostScrapeReport {
+            hostname: hostname.to_string(),
+            result: if ok {
+                Ok(HostScrapeStats {
+                    new_activations: Some(2),
+                    parse_errors: 1,
+                    spooled: false,
+                })
+            } else {
+                Err(ScrapeError::Fetch(
+                    reqwest::Client::new().get("not a url").build().unwrap_err(),
+                ))
+            },
+        }
+    }
+
+    #[test]
+    fn test_failure_ratio() {
+        let summary = ScrapeSummary::new(vec![
+            report("a", true),
+            report("b", false),
+            report("c", true),
+            report("d", true),
+        ]);
+        assert_eq!(summary.failure_ratio(), 0.25);
+        assert_eq!(summary.ok, 3);
+        assert_eq!(summary.failed, 1);
+    }
+
+    #[test]
+    fn test_spooled_hosts_are_not_delivered() {
+        let spooled = |hostname: &str| HostScrapeReport {
+            hostname: hostname.to_string(),
+            result: Ok(HostScrapeStats {
+                new_activations: None,
+                parse_errors: 0,
+                spooled: true,
+            }),
+        };
+        let summary = ScrapeSummary::new(vec![
+            report("a", false),
+            spooled("b"),
+            spooled("c"),
+            report("d", true),
+        ]);
+        assert_eq!(summary.ok, 1);
+        assert_eq!(summary.spooled, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.failure_ratio(), 0.75);
+        assert!(
+            summary
+                .render(SummaryFormat::Table)
+                .contains("c     spooled")
+        );
+    }
+
+    #[test]
+    fn test_empty_summary_has_no_failures() {
+        let summary = ScrapeSummary::new(vec![]);
+        assert_eq!(summary.failure_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_json_summary() {
+        let summary = ScrapeSummary::new(vec![report("a", true), report("b", false)]);
+        let json: serde_json::Value =
+            serde_json::from_str(&summary.render(SummaryFormat::Json)).unwrap();
+        assert_eq!(json["hosts"][0]["hostname"], "a");
+        assert_eq!(json["hosts"][0]["ok"], true);
+        assert_eq!(json["hosts"][0]["new_activations"], 2);
+        assert_eq!(json["hosts"][0]["parse_errors"], 1);
+        assert_eq!(json["hosts"][1]["ok"], false);
+        assert_eq!(json["hosts"][1]["error_kind"], "fetch");
+    }
+}
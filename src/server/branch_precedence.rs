@@ -0,0 +1,65 @@
+/// Ordered branch names or globs deciding which revision is shown for a store
+/// path built from several commits. A revision on a branch matching an earlier
+/// pattern wins, branches matching none come last. Ties go to the earliest linked.
+#[derive(Debug, Clone)]
+pub(crate) struct BranchPrecedence {
+    like_patterns: Vec<String>,
+}
+
+impl BranchPrecedence {
+    pub(crate) fn new(globs: &[String]) -> Self {
+        Self {
+            like_patterns: globs
+                .iter()
+                .map(|glob| glob.trim())
+                .filter(|glob| !glob.is_empty())
+                .map(glob_to_like)
+                .collect(),
+        }
+    }
+
+    /// The patterns for SQL `LIKE`, bound as a `text[]` and ranked by position.
+    pub(crate) fn like_patterns(&self) -> &[String] {
+        &self.like_patterns
+    }
+}
+
+/// `*` matches any run of characters and `?` a single one, everything else is literal.
+fn glob_to_like(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push('%'),
+            '?' => pattern.push('_'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_to_like() {
+        assert_eq!(glob_to_like("main"), "main");
+        assert_eq!(glob_to_like("release/*"), "release/%");
+        assert_eq!(glob_to_like("release-2?.*"), "release-2_.%");
+        assert_eq!(glob_to_like("feat_100%"), "feat\\_100\\%");
+    }
+
+    #[test]
+    fn test_blank_patterns_are_skipped() {
+        let precedence = BranchPrecedence::new(&[
+            "main".to_string(),
+            " ".to_string(),
+            " release/* ".to_string(),
+        ]);
+        assert_eq!(precedence.like_patterns(), ["main", "release/%"]);
+    }
+}
@@ -1,13 +1,24 @@
 use crate::{
-    server::ServerState,
-    shared::{dto::host::HostWithLogsDto, model::activation::NewActivation},
+    server::{
+        ServerState, custom_error::RetError, service::activation_service::InsertedActivations,
+    },
+    shared::{
+        dto::{activation::HistoryEntryDto, host::HostWithLogsDto},
+        model::activation::NewActivation,
+    },
 };
-use axum::{Json, extract::State};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::Deserialize;
 
 #[axum::debug_handler]
 pub(crate) async fn create_activation(
     State(ServerState {
         activation_log_service,
+        metrics,
+        unknown_provenance_webhook,
         ..
     }): State<ServerState>,
     Json(host_with_logs_dto): Json<HostWithLogsDto>,
@@ -18,11 +29,57 @@ pub(crate) async fn create_activation(
         .map(|dto| NewActivation::from((&host_with_logs_dto, dto.clone())))
         .collect();
     tracing::debug!(count = models.len(), "recieved activations");
-    let i = activation_log_service
+    let InsertedActivations {
+        inserted: i,
+        unknown_provenance,
+    } = activation_log_service
         .bulk_insert_log_records(models.as_ref())
         .await?;
+    metrics.observe_activations(models.len(), i);
+    if !unknown_provenance.is_empty() {
+        tracing::warn!(
+            host = %host_with_logs_dto.hostname,
+            count = unknown_provenance.len(),
+            "received activations of store paths with unknown provenance"
+        );
+        metrics.observe_unknown_provenance(&host_with_logs_dto.hostname, unknown_provenance.len());
+        if let Some(webhook) = unknown_provenance_webhook {
+            webhook.notify(HostWithLogsDto {
+                logs: unknown_provenance.into_iter().map(Into::into).collect(),
+                ..host_with_logs_dto
+            });
+        }
+    }
     if i > 0 {
         tracing::info!(count = i, "inserted activations");
     }
     Ok(format!("{i} log entries created"))
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryQuery {
+    rollback: Option<bool>,
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_activations(
+    State(ServerState {
+        host_service,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+    Path(hostname): Path<String>,
+    Query(params): Query<HistoryQuery>,
+) -> axum::response::Result<Json<Vec<HistoryEntryDto>>, RetError> {
+    let host = host_service
+        .get_host_from_hostname(hostname)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    let history = activation_log_service
+        .get_history(&host.hostname, params.rollback)
+        .await?
+        .into_iter()
+        .map(HistoryEntryDto::from)
+        .collect();
+    Ok(Json(history))
+}
@@ -0,0 +1,97 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::{dto::activity::ActivityPageDto, model::activity::ActivityFilter},
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+    response::Html,
+};
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 500;
+
+/// Empty values, as sent by the filter form, mean no filter.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ActivityQuery {
+    grouping_key: Option<String>,
+    group: Option<String>,
+    username: Option<String>,
+    branch: Option<String>,
+    activation_type: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
+
+impl ActivityQuery {
+    fn filter(&self, default_grouping_key: Option<&String>) -> ActivityFilter {
+        let non_empty = |v: &Option<String>| v.clone().filter(|v| !v.trim().is_empty());
+        let grouping_key = non_empty(&self.grouping_key).or(default_grouping_key.cloned());
+        ActivityFilter {
+            group: grouping_key.zip(non_empty(&self.group)),
+            username: non_empty(&self.username),
+            branch: non_empty(&self.branch),
+            activation_type: non_empty(&self.activation_type),
+        }
+    }
+
+    fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn per_page(&self) -> u32 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+}
+
+async fn activity_page(
+    server_state: &ServerState,
+    params: &ActivityQuery,
+) -> Result<ActivityPageDto, RetError> {
+    let filter = params.filter(server_state.server_config.default_grouping_key());
+    let (entries, has_more) = server_state
+        .activation_log_service
+        .get_activity(&filter, params.page(), params.per_page())
+        .await?;
+    Ok(ActivityPageDto {
+        entries: entries.into_iter().map(Into::into).collect(),
+        page: params.page(),
+        per_page: params.per_page(),
+        has_more,
+    })
+}
+
+#[axum::debug_handler]
+pub async fn render_activity_page(
+    State(server_state): State<ServerState>,
+    Query(params): Query<ActivityQuery>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let activity = activity_page(&server_state, &params).await?;
+
+    let mut ctx = Context::new();
+    ctx.insert("title", "activity");
+    ctx.insert(
+        "default_grouping_key",
+        &server_state.server_config.default_grouping_key(),
+    );
+    ctx.insert("query", &params);
+    ctx.insert("activity_ctx", &activity);
+    Ok(Html(
+        server_state
+            .tera
+            .render("activity.html.tera", &ctx)
+            .unwrap(),
+    ))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_activity(
+    State(server_state): State<ServerState>,
+    Query(params): Query<ActivityQuery>,
+) -> axum::response::Result<Json<ActivityPageDto>, RetError> {
+    Ok(Json(activity_page(&server_state, &params).await?))
+}
@@ -0,0 +1,63 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::annotation::{AnnotationContentDto, AnnotationDto, NewAnnotationDto},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+#[axum::debug_handler]
+pub(crate) async fn create_annotation(
+    State(ServerState {
+        annotation_service, ..
+    }): State<ServerState>,
+    Path(hostname): Path<String>,
+    Json(annotation): Json<NewAnnotationDto>,
+) -> axum::response::Result<impl IntoResponse, RetError> {
+    let annotation = annotation_service.create(&hostname, annotation).await?;
+    tracing::info!(
+        host = hostname,
+        annotation_id = annotation.annotation_id,
+        author = annotation.author,
+        "created annotation"
+    );
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn update_annotation(
+    State(ServerState {
+        annotation_service, ..
+    }): State<ServerState>,
+    Path(annotation_id): Path<i64>,
+    Json(content): Json<AnnotationContentDto>,
+) -> axum::response::Result<Json<AnnotationDto>, RetError> {
+    let annotation = annotation_service.update(annotation_id, content).await?;
+    Ok(Json(annotation))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn expire_annotation(
+    State(ServerState {
+        annotation_service, ..
+    }): State<ServerState>,
+    Path(annotation_id): Path<i64>,
+) -> axum::response::Result<Json<AnnotationDto>, RetError> {
+    let annotation = annotation_service.expire(annotation_id).await?;
+    Ok(Json(annotation))
+}
+
+/// Every note on a host and its activations, expired ones too.
+#[axum::debug_handler]
+pub(crate) async fn get_annotations(
+    State(ServerState {
+        annotation_service, ..
+    }): State<ServerState>,
+    Path(hostname): Path<String>,
+) -> axum::response::Result<Json<Vec<AnnotationDto>>, RetError> {
+    let annotations = annotation_service.get_by_hostname(&hostname).await?;
+    Ok(Json(annotations))
+}
@@ -0,0 +1,68 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::desired_revision::{DesiredRevisionDto, HostComplianceDto, SetDesiredRevisionDto},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+#[axum::debug_handler]
+pub(crate) async fn set_desired_revision(
+    State(ServerState {
+        desired_revision_service,
+        ..
+    }): State<ServerState>,
+    Path((grouping_key, group_value)): Path<(String, String)>,
+    Json(desired): Json<SetDesiredRevisionDto>,
+) -> axum::response::Result<Json<DesiredRevisionDto>, RetError> {
+    let desired = desired_revision_service
+        .set(grouping_key, group_value, desired)
+        .await?;
+    tracing::info!(
+        grouping_key = desired.grouping_key,
+        group = desired.group_value,
+        author = desired.author,
+        "set desired revision"
+    );
+    Ok(Json(desired))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn clear_desired_revision(
+    State(ServerState {
+        desired_revision_service,
+        ..
+    }): State<ServerState>,
+    Path((grouping_key, group_value)): Path<(String, String)>,
+) -> axum::response::Result<StatusCode, RetError> {
+    desired_revision_service
+        .clear(&grouping_key, &group_value)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_desired_revisions(
+    State(ServerState {
+        desired_revision_service,
+        ..
+    }): State<ServerState>,
+) -> axum::response::Result<Json<Vec<DesiredRevisionDto>>, RetError> {
+    Ok(Json(desired_revision_service.get_all().await?))
+}
+
+/// Every host in a group with a desired revision, and whether it runs it.
+#[axum::debug_handler]
+pub(crate) async fn get_compliance(
+    State(ServerState {
+        host_service,
+        desired_revision_service,
+        ..
+    }): State<ServerState>,
+) -> axum::response::Result<Json<Vec<HostComplianceDto>>, RetError> {
+    let hosts = host_service.get_all_with_state().await?;
+    let compliance = desired_revision_service.compliance(&hosts).await?;
+    Ok(Json(compliance.into_iter().map(Into::into).collect()))
+}
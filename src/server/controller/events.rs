@@ -0,0 +1,30 @@
+use crate::server::ServerState;
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Server-sent `host` events with the new state of a host whenever it gets activations.
+#[axum::debug_handler]
+pub(crate) async fn stream_host_events(
+    State(ServerState { host_events, .. }): State<ServerState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let receiver = host_events.subscribe();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(host) => {
+                    let event = Event::default().event("host").json_data(&host);
+                    return Some((event, receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "event subscriber fell behind");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,131 @@
+use chrono::Utc;
+
+use crate::{
+    server::{
+        ServerState, configured_time_zone,
+        controller::frontpage::FrontPageQuery,
+        custom_error::RetError,
+        export::{ExportFormat, FrontpageExport, HistoryExport},
+        grouping::GroupingKey,
+    },
+    shared::dto::{activation::HistoryEntryDto, host::HostStateDto},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct FrontpageExportQuery {
+    #[serde(flatten)]
+    frontpage: FrontPageQuery,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct HistoryExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+fn export_response(
+    format: ExportFormat,
+    filename: &str,
+    json: impl Serialize,
+    csv: impl FnOnce() -> String,
+) -> Response {
+    match format {
+        ExportFormat::Json => Json(json).into_response(),
+        ExportFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{filename}.csv\""),
+                ),
+            ],
+            csv(),
+        )
+            .into_response(),
+    }
+}
+
+/// The hosts of the frontpage with the same grouping, filter and columns.
+#[axum::debug_handler]
+pub(crate) async fn export_frontpage(
+    State(ServerState {
+        server_config,
+        host_service,
+        ..
+    }): State<ServerState>,
+    Query(params): Query<FrontpageExportQuery>,
+) -> Result<Response, RetError> {
+    let grouping_keys = params
+        .frontpage
+        .grouping_keys(&server_config.default_grouping_keys);
+    let parsed_keys = grouping_keys
+        .iter()
+        .map(|key| GroupingKey::parse(key))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (query, sort) = params.frontpage.host_query()?;
+    let hosts: Vec<HostStateDto> = host_service
+        .get_matching(&query, &sort)
+        .await?
+        .into_iter()
+        .map(HostStateDto::from)
+        .collect();
+
+    let export = FrontpageExport::new(
+        hosts,
+        &grouping_keys,
+        &parsed_keys,
+        &server_config.columns,
+        configured_time_zone(),
+        Utc::now(),
+    );
+    Ok(export_response(params.format, "hosts", &export, || {
+        export.to_csv()
+    }))
+}
+
+/// Every activation of a host, as on its history page.
+#[axum::debug_handler]
+pub(crate) async fn export_history(
+    State(ServerState {
+        host_service,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+    Path(hostname): Path<String>,
+    Query(params): Query<HistoryExportQuery>,
+) -> Result<Response, RetError> {
+    let host = host_service
+        .get_host_from_hostname(hostname)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    let activations_by_date = activation_log_service
+        .host_with_logs_by_hostname(&host.hostname)
+        .await?
+        .into_iter()
+        .map(|(date, entries)| {
+            (
+                date,
+                entries.into_iter().map(HistoryEntryDto::from).collect(),
+            )
+        })
+        .collect();
+
+    let export = HistoryExport::new(
+        host.hostname.clone(),
+        activations_by_date,
+        configured_time_zone(),
+    );
+    let filename = format!("history-{}", host.hostname);
+    Ok(export_response(params.format, &filename, &export, || {
+        export.to_csv()
+    }))
+}
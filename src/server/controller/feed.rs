@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::{dto::activation::ActivationDto, model::activation::Activation},
+};
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use tera::{Context, Tera};
+
+/// Activations in a feed, feed readers only look at the latest ones.
+const FEED_LENGTH: i64 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+struct FeedEntry {
+    activation_id: i64,
+    hostname: String,
+    activation: ActivationDto,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FeedContext {
+    title: String,
+    /// Path of the feed, also its id.
+    path: String,
+    /// The newest activation, or now for an empty feed.
+    updated: DateTime<Utc>,
+    entries: Vec<FeedEntry>,
+}
+
+fn render_feed(tera: &Tera, title: String, path: String, activations: Vec<Activation>) -> Response {
+    let mut activations = activations;
+    activations
+        .sort_by_key(|activation| std::cmp::Reverse((activation.core.activated_at, activation.id)));
+    let updated = activations
+        .first()
+        .map(|activation| activation.core.activated_at)
+        .unwrap_or_else(Utc::now);
+    let entries = activations
+        .into_iter()
+        .map(|activation| FeedEntry {
+            activation_id: activation.id,
+            hostname: activation.core.hostname.clone(),
+            activation: activation.into(),
+        })
+        .collect();
+    let feed_ctx = FeedContext {
+        title,
+        path,
+        updated,
+        entries,
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert("feed_ctx", &feed_ctx);
+    (
+        [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        tera.render("feed.atom.tera", &ctx).unwrap(),
+    )
+        .into_response()
+}
+
+/// Activations of every host.
+#[axum::debug_handler]
+pub async fn get_fleet_feed(
+    State(ServerState {
+        tera,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+) -> Result<Response, RetError> {
+    let activations = activation_log_service
+        .get_recent(None, None, FEED_LENGTH)
+        .await?;
+    Ok(render_feed(
+        &tera,
+        "hostmap activations".to_string(),
+        "/feed".to_string(),
+        activations,
+    ))
+}
+
+#[axum::debug_handler]
+pub async fn get_host_feed(
+    State(ServerState {
+        tera,
+        host_service,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+    Path(hostname): Path<String>,
+) -> Result<Response, RetError> {
+    let host = host_service
+        .get_host_from_hostname(hostname)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    let activations = activation_log_service
+        .get_recent(Some(&host.hostname), None, FEED_LENGTH)
+        .await?;
+    Ok(render_feed(
+        &tera,
+        format!("hostmap activations of {}", host.hostname),
+        format!("/feed/hosts/{}", host.hostname),
+        activations,
+    ))
+}
+
+/// Activations of the hosts whose metadata has `value` for `key`.
+#[axum::debug_handler]
+pub async fn get_group_feed(
+    State(ServerState {
+        tera,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+    Path((key, value)): Path<(String, String)>,
+) -> Result<Response, RetError> {
+    let activations = activation_log_service
+        .get_recent(None, Some((&key, &value)), FEED_LENGTH)
+        .await?;
+    Ok(render_feed(
+        &tera,
+        format!("hostmap activations of {key} {value}"),
+        format!("/feed/groups/{key}/{value}"),
+        activations,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        server::{forge::ForgeLinks, load_tera},
+        shared::model::activation::ActivationCore,
+    };
+    use axum::body::to_bytes;
+    use chrono::TimeZone;
+
+    fn activation(id: i64, hour: u32, hostname: &str, username: &str) -> Activation {
+        Activation {
+            id,
+            core: ActivationCore {
+                activated_at: Utc.with_ymd_and_hms(2026, 1, 5, hour, 0, 0).unwrap(),
+                username: username.to_string(),
+                store_path: format!("/nix/store/{id}-nixos-system"),
+                activation_type: "switch".to_string(),
+                hostname: hostname.to_string(),
+                revision: None,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_feed_is_escaped_atom_newest_first() {
+        let forge_links =
+            ForgeLinks::new(None, None, None, Some("https://git".to_string())).unwrap();
+        let tera = load_tera("templates", &forge_links).unwrap();
+        let response = render_feed(
+            &tera,
+            "hostmap activations of <env> & co".to_string(),
+            "/feed".to_string(),
+            vec![
+                activation(1, 9, "a", "root"),
+                activation(3, 14, "b<i>&\"", "eve</name>"),
+                activation(2, 12, "c", "root"),
+            ],
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        let document = roxmltree::Document::parse(&body).unwrap();
+        let feed = document.root_element();
+        assert_eq!(feed.tag_name().name(), "feed");
+        assert_eq!(
+            feed.tag_name().namespace(),
+            Some("http://www.w3.org/2005/Atom")
+        );
+        let child = |node: roxmltree::Node<'_, '_>, name: &str| {
+            node.children()
+                .find(|child| child.has_tag_name(name))
+                .and_then(|child| child.text())
+                .unwrap_or_default()
+                .to_string()
+        };
+        assert_eq!(child(feed, "title"), "hostmap activations of <env> & co");
+        assert_eq!(child(feed, "updated"), "2026-01-05T14:00:00Z");
+
+        let entries: Vec<roxmltree::Node> = feed
+            .children()
+            .filter(|node| node.has_tag_name("entry"))
+            .collect();
+        let ids: Vec<String> = entries.iter().map(|entry| child(*entry, "id")).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "urn:hostmap:activation:3",
+                "urn:hostmap:activation:2",
+                "urn:hostmap:activation:1"
+            ]
+        );
+        assert_eq!(
+            child(entries[0], "title"),
+            "b<i>&\": switch /nix/store/3-nixos-system by eve</name>"
+        );
+        // the html content is escaped once more, as text of the element
+        let content = child(entries[0], "content");
+        assert!(
+            content.contains("<b>b&lt;i&gt;&amp;&quot;</b>"),
+            "{content}"
+        );
+        assert!(!content.contains("<i>"), "{content}");
+    }
+}
@@ -2,12 +2,25 @@ use std::collections::BTreeMap;
 
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
+
 use crate::{
-    server::{ServerState, custom_error::RetError},
-    shared::dto::host::CurrentHostDto,
+    server::{
+        ServerState,
+        custom_error::RetError,
+        grouping::GroupingKey,
+        host_query::{HostQuery, HostSort, QueryError, parse_host_query},
+    },
+    shared::{
+        dto::{
+            desired_revision::{DesiredRevisionDto, HostComplianceDto},
+            host::HostStateDto,
+        },
+        model::{desired_revision::ComplianceStatus, host::HostWithState},
+    },
 };
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     response::Html,
 };
 use serde::{Deserialize, Serialize};
@@ -15,17 +28,39 @@ use tera::Context;
 
 #[derive(Debug, Clone, Serialize)]
 struct FrontPageContext {
-    hosts: Vec<CurrentHostDto>,
+    hosts: Vec<HostStateDto>,
 }
 
 impl FrontPageContext {
-    fn new(hosts: Vec<CurrentHostDto>) -> Self {
+    fn new(hosts: Vec<HostStateDto>) -> Self {
         Self { hosts }
     }
 }
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FrontPageQuery {
     grouping_key: Option<String>,
+    /// Filter expression, see [`crate::server::host_query`].
+    q: Option<String>,
+    sort: Option<String>,
+}
+
+impl FrontPageQuery {
+    /// `?grouping_key=` with nothing in it shows every host ungrouped.
+    pub(crate) fn grouping_keys(&self, defaults: &[String]) -> Vec<String> {
+        match &self.grouping_key {
+            Some(keys) => keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => defaults.to_vec(),
+        }
+    }
+
+    pub(crate) fn host_query(&self) -> Result<(HostQuery, HostSort), QueryError> {
+        parse_host_query(self.q.as_deref(), self.sort.as_deref())
+    }
 }
 
 #[axum::debug_handler]
@@ -33,42 +68,122 @@ pub async fn render_frontpage(
     State(server_state): State<ServerState>,
     Query(params): Query<FrontPageQuery>,
 ) -> axum::response::Result<Html<String>, RetError> {
-    let grouping_key = params
-        .grouping_key
-        .as_ref()
-        .or(server_state.server_config.default_grouping_key.as_ref())
-        .cloned();
+    let grouping_keys = params.grouping_keys(&server_state.server_config.default_grouping_keys);
 
     let mut ctx = Context::new();
-    ctx.insert("columns", &server_state.server_config.columns);
-    ctx.insert("repo_url", &server_state.server_config.repo_url);
+    ctx.insert("query", &params);
+    ctx.insert("feed_url", "/feed");
+
+    // a broken expression is shown next to the form instead of failing the page
+    let (query, sort) = match params.host_query() {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            ctx.insert("query_error", &err.to_string());
+            (HostQuery::default(), HostSort::default())
+        }
+    };
+    let hosts = server_state
+        .host_service
+        .get_matching(&query, &sort)
+        .await?;
+    let compliance = insert_host_rows_context(&server_state, &hosts, &mut ctx).await?;
 
-    if let Some(grouping_key) = grouping_key {
-        render_frontpage_by_group(&grouping_key, server_state, ctx).await
-    } else {
-        render_frontpage_all_hosts(server_state, ctx).await
+    let parsed_keys = grouping_keys
+        .iter()
+        .map(|key| GroupingKey::parse(key))
+        .collect::<Result<Vec<_>, _>>();
+    match parsed_keys {
+        Ok(parsed_keys) if !parsed_keys.is_empty() => {
+            render_frontpage_by_group(
+                &grouping_keys,
+                &parsed_keys,
+                hosts,
+                &compliance,
+                server_state,
+                ctx,
+            )
+            .await
+        }
+        Ok(_) => render_frontpage_all_hosts(hosts, server_state, ctx).await,
+        Err(err) => {
+            ctx.insert("query_error", &err.to_string());
+            render_frontpage_all_hosts(hosts, server_state, ctx).await
+        }
+    }
+}
+
+/// What `host.html.tera` needs besides the hosts, returns the compliance of the hosts.
+async fn insert_host_rows_context(
+    server_state: &ServerState,
+    hosts: &[HostWithState],
+    ctx: &mut Context,
+) -> Result<HashMap<String, HostComplianceDto>, RetError> {
+    ctx.insert("columns", &server_state.server_config.columns);
+    ctx.insert("input_columns", &server_state.server_config.input_columns);
+    let annotations = server_state.annotation_service.get_active_by_host().await?;
+    ctx.insert("annotations", &annotations);
+    let compliance: HashMap<String, HostComplianceDto> = server_state
+        .desired_revision_service
+        .compliance(hosts)
+        .await?
+        .into_iter()
+        .map(|host| (host.hostname.clone(), host.into()))
+        .collect();
+    ctx.insert("compliance", &compliance);
+    if let Some(git_repository) = &server_state.git_repository {
+        let mut commit_hashes: Vec<String> = hosts
+            .iter()
+            .flat_map(|host| {
+                let running = host.running.as_ref();
+                let running = running.and_then(|activation| activation.core.revision.as_ref());
+                let latest_build = host.latest_build.as_ref().map(|build| &build.revision);
+                running.into_iter().chain(latest_build)
+            })
+            .map(|revision| revision.commit_hash.clone())
+            .collect();
+        commit_hashes.sort();
+        commit_hashes.dedup();
+        ctx.insert("commits", &git_repository.commits(commit_hashes).await);
     }
+    Ok(compliance)
+}
+
+/// The frontpage row of one host, for the live updates to swap in. The commit
+/// colors depend on the page, so the row leaves them to the page.
+#[axum::debug_handler]
+pub async fn render_host_row(
+    State(server_state): State<ServerState>,
+    Path(hostname): Path<String>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let host = server_state
+        .host_service
+        .get_with_state(&hostname)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    let mut ctx = Context::new();
+    insert_host_rows_context(&server_state, std::slice::from_ref(&host), &mut ctx).await?;
+    ctx.insert("host", &HostStateDto::from(host));
+    ctx.insert("color_map", &HashMap::<String, String>::new());
+    Ok(Html(
+        server_state.tera.render("host.html.tera", &ctx).unwrap(),
+    ))
 }
 
 async fn render_frontpage_all_hosts(
-    ServerState {
-        tera, host_service, ..
-    }: ServerState,
+    host_models: Vec<HostWithState>,
+    ServerState { tera, .. }: ServerState,
     mut ctx: Context,
 ) -> axum::response::Result<Html<String>, RetError> {
-    let host_models = host_service
-        .get_all_with_latest_log()
-        .await
-        .expect("Failed to fetch hosts");
     let hosts = host_models
         .into_iter()
-        .map(|hwl| CurrentHostDto::from((hwl.host, hwl.logs)))
-        .collect::<Vec<CurrentHostDto>>();
+        .map(HostStateDto::from)
+        .collect::<Vec<HostStateDto>>();
 
     let commit_hashes: Vec<String> = hosts
         .iter()
         .filter_map(|h| {
-            h.logs
+            h.host
+                .logs
                 .as_ref()
                 .and_then(|l| l.revision.as_ref())
                 .map(|r| r.commit_hash.clone())
@@ -85,58 +200,246 @@ async fn render_frontpage_all_hosts(
     Ok(Html(output))
 }
 
+/// How far the hosts of a group are from running the same commit. Hosts
+/// running a store path CI never linked count as one commit of their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+struct Drift {
+    commits: usize,
+    /// The commit most hosts run.
+    majority_commit: Option<String>,
+    /// Hosts not running `majority_commit`.
+    off_majority: usize,
+}
+
+impl Drift {
+    fn new<'a>(hosts: impl Iterator<Item = &'a HostStateDto>) -> Self {
+        let mut by_commit: BTreeMap<Option<&str>, usize> = BTreeMap::new();
+        let mut total = 0;
+        for host in hosts {
+            let commit = host
+                .host
+                .logs
+                .as_ref()
+                .and_then(|l| l.revision.as_ref())
+                .map(|r| r.commit_hash.as_str());
+            *by_commit.entry(commit).or_default() += 1;
+            total += 1;
+        }
+        let majority = by_commit
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)));
+        Self {
+            commits: by_commit.len(),
+            majority_commit: majority.and_then(|(commit, _)| commit.map(str::to_string)),
+            off_majority: total - majority.map(|(_, count)| *count).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HostGroup {
+    name: String,
+    children: Vec<HostGroup>,
+    /// Only filled at the innermost level.
+    hosts: Vec<HostStateDto>,
+}
+
+impl HostGroup {
+    /// Nests the hosts by the value of each key in turn, keeping their order within a group.
+    fn build(hosts: Vec<HostStateDto>, keys: &[GroupingKey], now: DateTime<Utc>) -> Vec<HostGroup> {
+        let Some((key, rest)) = keys.split_first() else {
+            return Vec::new();
+        };
+        let mut by_name: BTreeMap<(usize, String), Vec<HostStateDto>> = BTreeMap::new();
+        for host in hosts {
+            by_name
+                .entry(key.group_of(&host, now))
+                .or_default()
+                .push(host);
+        }
+        by_name
+            .into_iter()
+            .map(|((_, name), hosts)| {
+                if rest.is_empty() {
+                    HostGroup {
+                        name,
+                        children: Vec::new(),
+                        hosts,
+                    }
+                } else {
+                    HostGroup {
+                        name,
+                        children: Self::build(hosts, rest, now),
+                        hosts: Vec::new(),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn all_hosts(&self) -> Box<dyn Iterator<Item = &HostStateDto> + '_> {
+        Box::new(
+            self.hosts
+                .iter()
+                .chain(self.children.iter().flat_map(|child| child.all_hosts())),
+        )
+    }
+}
+
+/// How the hosts of a group are doing on the desired revision of the group.
+#[derive(Debug, Clone, Serialize)]
+struct SectionCompliance {
+    desired: DesiredRevisionDto,
+    on_target: usize,
+    behind: usize,
+    unknown: usize,
+    overdue: usize,
+}
+
+impl SectionCompliance {
+    /// None if no host of the group follows a desired revision of it, hosts
+    /// in several groups with one follow the newest.
+    fn new<'a>(
+        key: &str,
+        name: &str,
+        hosts: impl Iterator<Item = &'a HostStateDto>,
+        compliance: &HashMap<String, HostComplianceDto>,
+    ) -> Option<Self> {
+        let mut section: Option<Self> = None;
+        for host in hosts {
+            let Some(host) = compliance.get(&host.host.hostname) else {
+                continue;
+            };
+            if host.desired.grouping_key != key || host.desired.group_value != name {
+                continue;
+            }
+            let section = section.get_or_insert_with(|| Self {
+                desired: host.desired.clone(),
+                on_target: 0,
+                behind: 0,
+                unknown: 0,
+                overdue: 0,
+            });
+            match host.status {
+                ComplianceStatus::OnTarget => section.on_target += 1,
+                ComplianceStatus::Behind => section.behind += 1,
+                ComplianceStatus::Unknown => section.unknown += 1,
+            }
+            section.overdue += usize::from(host.overdue);
+        }
+        section
+    }
+}
+
+/// A group on the page. Groups come in document order, a group is nested in
+/// the groups before it with a smaller depth.
+#[derive(Debug, Clone, Serialize)]
+struct GroupSection {
+    name: String,
+    /// Grouping key the name is a value of.
+    key: String,
+    depth: usize,
+    /// Open sections to close before this one starts.
+    closes_before: usize,
+    total_hosts: usize,
+    rollbacks: i64,
+    drift: Drift,
+    compliance: Option<SectionCompliance>,
+    hosts: Vec<HostStateDto>,
+}
+
+fn flatten_groups(
+    groups: Vec<HostGroup>,
+    keys: &[String],
+    compliance: &HashMap<String, HostComplianceDto>,
+    depth: usize,
+    open: &mut usize,
+    sections: &mut Vec<GroupSection>,
+) {
+    for group in groups {
+        let section = GroupSection {
+            compliance: SectionCompliance::new(
+                &keys[depth],
+                &group.name,
+                group.all_hosts(),
+                compliance,
+            ),
+            key: keys[depth].clone(),
+            depth,
+            closes_before: *open - depth,
+            total_hosts: group.all_hosts().count(),
+            rollbacks: group.all_hosts().map(|host| host.rollbacks).sum(),
+            drift: Drift::new(group.all_hosts()),
+            name: group.name,
+            hosts: group.hosts,
+        };
+        *open = depth + 1;
+        sections.push(section);
+        flatten_groups(group.children, keys, compliance, depth + 1, open, sections);
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct FrontpageGroupedContext {
-    groups: BTreeMap<String, Vec<CurrentHostDto>>,
+    grouping_keys: Vec<String>,
+    sections: Vec<GroupSection>,
+    /// Sections left open after the last one.
+    closes_after: usize,
+    /// Innermost groups.
     total_groups: usize,
     total_hosts: usize,
+    drift: Drift,
 }
 
 impl FrontpageGroupedContext {
-    fn new(groups: BTreeMap<String, Vec<CurrentHostDto>>) -> Self {
-        let total_groups = groups.len();
-        let mut total_hosts = 0;
-        for group in groups.values() {
-            total_hosts += group.len();
-        }
-        Self {
+    fn new(
+        hosts: Vec<HostStateDto>,
+        grouping_keys: &[String],
+        parsed_keys: &[GroupingKey],
+        compliance: &HashMap<String, HostComplianceDto>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let total_hosts = hosts.len();
+        let drift = Drift::new(hosts.iter());
+        let groups = HostGroup::build(hosts, parsed_keys, now);
+        let mut sections = Vec::new();
+        let mut open = 0;
+        flatten_groups(
             groups,
+            grouping_keys,
+            compliance,
+            0,
+            &mut open,
+            &mut sections,
+        );
+        let total_groups = sections
+            .iter()
+            .filter(|section| section.depth + 1 == grouping_keys.len())
+            .count();
+        Self {
+            grouping_keys: grouping_keys.to_vec(),
+            sections,
+            closes_after: open,
             total_groups,
             total_hosts,
+            drift,
         }
     }
 }
 async fn render_frontpage_by_group(
-    grouping_key: &str,
-    ServerState {
-        tera, host_service, ..
-    }: ServerState,
+    grouping_keys: &[String],
+    parsed_keys: &[GroupingKey],
+    host_with_logs: Vec<HostWithState>,
+    compliance: &HashMap<String, HostComplianceDto>,
+    ServerState { tera, .. }: ServerState,
     mut ctx: Context,
 ) -> axum::response::Result<Html<String>, RetError> {
-    let host_with_logs = host_service.get_all_with_latest_log().await.unwrap();
-
-    let mut grouped_hosts: BTreeMap<String, Vec<CurrentHostDto>> = BTreeMap::new();
-
-    for host_with_log in host_with_logs {
-        let current_host_dto =
-            CurrentHostDto::from((host_with_log.host.clone(), host_with_log.logs.clone()));
-        let group_name = host_with_log
-            .host
-            .metadata
-            .get(grouping_key)
-            .map(|v| v.to_owned())
-            .unwrap_or_else(|| "Ungrouped".to_string());
-
-        grouped_hosts
-            .entry(group_name)
-            .or_default()
-            .push(current_host_dto);
-    }
-    let commit_hashes: Vec<String> = grouped_hosts
-        .values()
-        .flatten()
+    let hosts: Vec<HostStateDto> = host_with_logs.into_iter().map(HostStateDto::from).collect();
+    let commit_hashes: Vec<String> = hosts
+        .iter()
         .filter_map(|h| {
-            h.logs
+            h.host
+                .logs
                 .as_ref()
                 .and_then(|l| l.revision.as_ref())
                 .map(|r| r.commit_hash.clone())
@@ -145,8 +448,12 @@ async fn render_frontpage_by_group(
 
     let color_map = build_color_map_for_hashes(commit_hashes);
 
-    let fp_ctx = FrontpageGroupedContext::new(grouped_hosts);
-    ctx.insert("title", &format!("frontpage by group: {}", grouping_key));
+    let fp_ctx =
+        FrontpageGroupedContext::new(hosts, grouping_keys, parsed_keys, compliance, Utc::now());
+    ctx.insert(
+        "title",
+        &format!("frontpage by group: {}", grouping_keys.join(" / ")),
+    );
     ctx.insert("grouped_frontpage_ctx", &fp_ctx);
     ctx.insert("color_map", &color_map);
 
@@ -174,3 +481,93 @@ pub(crate) fn build_color_map_for_hashes(hashes: Vec<String>) -> HashMap<String,
     }
     color_map
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::model::{
+        activation::{Activation, ActivationCore},
+        host::HostModel,
+        revision::RevisionModel,
+    };
+    use chrono::TimeZone;
+
+    fn host(hostname: &str, env: &str, group: &str, commit: Option<&str>) -> HostStateDto {
+        HostStateDto::from(HostWithState {
+            host: HostModel {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::from([
+                    ("env".to_string(), env.to_string()),
+                    ("group".to_string(), group.to_string()),
+                ]),
+            },
+            running: Some(Activation {
+                id: 1,
+                core: ActivationCore {
+                    activated_at: Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap(),
+                    username: "root".to_string(),
+                    store_path: format!("/nix/store/{hostname}"),
+                    activation_type: "switch".to_string(),
+                    hostname: hostname.to_string(),
+                    revision: commit.map(|commit| RevisionModel {
+                        commit_hash: commit.to_string(),
+                        branch: "master".to_string(),
+                    }),
+                },
+            }),
+            next_boot: None,
+            latest_test: None,
+            rollbacks: 1,
+            latest_build: None,
+            running_inputs: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_nested_groups() {
+        let hosts = vec![
+            host("a", "prod", "web", Some("new")),
+            host("b", "prod", "db", Some("old")),
+            host("c", "prod", "web", Some("new")),
+            host("d", "staging", "web", None),
+        ];
+        let keys = vec!["env".to_string(), "group".to_string()];
+        let parsed_keys: Vec<GroupingKey> = keys
+            .iter()
+            .map(|key| GroupingKey::parse(key).unwrap())
+            .collect();
+        let ctx =
+            FrontpageGroupedContext::new(hosts, &keys, &parsed_keys, &HashMap::new(), Utc::now());
+
+        let outline: Vec<(usize, &str, usize, usize)> = ctx
+            .sections
+            .iter()
+            .map(|s| (s.depth, s.name.as_str(), s.total_hosts, s.closes_before))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                (0, "prod", 3, 0),
+                (1, "db", 1, 0),
+                (1, "web", 2, 1),
+                (0, "staging", 1, 2),
+                (1, "web", 1, 0),
+            ]
+        );
+        assert_eq!(ctx.closes_after, 2);
+        assert_eq!(ctx.total_groups, 3);
+        assert_eq!(ctx.sections[0].rollbacks, 3);
+        assert!(ctx.sections[0].hosts.is_empty());
+        assert_eq!(ctx.sections[2].hosts.len(), 2);
+        assert_eq!(
+            ctx.sections[0].drift,
+            Drift {
+                commits: 2,
+                majority_commit: Some("new".to_string()),
+                off_majority: 1,
+            }
+        );
+        assert_eq!(ctx.drift.commits, 3);
+    }
+}
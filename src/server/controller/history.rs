@@ -1,8 +1,8 @@
 use crate::{
-    server::{ServerState, custom_error::RetError},
+    server::{ServerState, custom_error::RetError, git::RangeQuery},
     shared::{
-        dto::{activation::ActivationDto, host::CurrentHostDto},
-        model::activation::Activation,
+        dto::{activation::HistoryEntryDto, host::CurrentHostDto},
+        model::activation::DEPLOY_ACTIVATION_TYPES,
     },
 };
 use axum::{
@@ -11,33 +11,103 @@ use axum::{
 };
 use chrono::NaiveDate;
 use serde::Serialize;
+use std::collections::HashSet;
 use tera::Context;
 
 #[derive(Debug, Clone, Serialize)]
 struct HistoryPageContext {
     host: CurrentHostDto,
-    activations_by_date: Vec<(NaiveDate, Vec<ActivationDto>)>,
+    activations_by_date: Vec<(NaiveDate, Vec<HistoryEntryDto>)>,
+    rollbacks: usize,
 }
 
 impl HistoryPageContext {
     fn new(
         host: CurrentHostDto,
-        activations_by_date: Vec<(NaiveDate, Vec<ActivationDto>)>,
+        activations_by_date: Vec<(NaiveDate, Vec<HistoryEntryDto>)>,
     ) -> Self {
+        let rollbacks = activations_by_date
+            .iter()
+            .flat_map(|(_, entries)| entries)
+            .filter(|entry| entry.is_rollback)
+            .count();
         Self {
             host,
             activations_by_date,
+            rollbacks,
         }
     }
 }
 
+/// A deploy that changed the store path, and the deploy before it.
+#[derive(Debug, Clone, Serialize)]
+struct StorePathChange {
+    activation_id: i64,
+    previous_activation_id: i64,
+}
+
+/// Whether the activation put its store path on the host, unlike `dry-activate`.
+fn is_deploy(entry: &HistoryEntryDto) -> bool {
+    DEPLOY_ACTIVATION_TYPES.contains(&entry.activation.activation_type.as_str())
+}
+
+/// Every deploy that changed the store path, compared with the deploy before
+/// it, where both store paths have a manifest to diff. `entries` are oldest first.
+fn store_path_changes(
+    entries: &[&HistoryEntryDto],
+    with_manifest: &HashSet<String>,
+) -> Vec<StorePathChange> {
+    let deploys: Vec<&HistoryEntryDto> = entries
+        .iter()
+        .copied()
+        .filter(|entry| is_deploy(entry))
+        .collect();
+    deploys
+        .windows(2)
+        .filter(|pair| {
+            let (previous, current) = (&pair[0].activation, &pair[1].activation);
+            previous.store_path != current.store_path
+                && with_manifest.contains(&previous.store_path)
+                && with_manifest.contains(&current.store_path)
+        })
+        .map(|pair| StorePathChange {
+            activation_id: pair[1].activation_id,
+            previous_activation_id: pair[0].activation_id,
+        })
+        .collect()
+}
+
+/// Every deploy that changed the commit, compared with the deploy before it.
+/// `entries` are oldest first.
+fn commit_changes(entries: &[&HistoryEntryDto]) -> Vec<RangeQuery> {
+    let revisions: Vec<(i64, &String)> = entries
+        .iter()
+        .filter(|entry| is_deploy(entry))
+        .filter_map(|entry| {
+            let revision = entry.activation.revision.as_ref()?;
+            Some((entry.activation_id, &revision.commit_hash))
+        })
+        .collect();
+    revisions
+        .windows(2)
+        .filter(|pair| pair[0].1 != pair[1].1)
+        .map(|pair| RangeQuery {
+            activation_id: pair[1].0,
+            from: pair[0].1.clone(),
+            to: pair[1].1.clone(),
+        })
+        .collect()
+}
+
 #[axum::debug_handler]
 pub async fn render_history_page(
     State(ServerState {
         tera,
-        server_config,
         host_service,
         activation_log_service,
+        annotation_service,
+        git_repository,
+        package_service,
         ..
     }): State<ServerState>,
     Path(hostname): Path<String>,
@@ -56,19 +126,137 @@ pub async fn render_history_page(
     for (date, entries) in date_map {
         let mut dto_vec = Vec::new();
         for entry in entries {
-            let activation: Activation = entry.into();
-            let dto = ActivationDto::from(activation);
-            dto_vec.push(dto);
+            dto_vec.push(HistoryEntryDto::from(entry));
         }
         date_dto_vec.push((date, dto_vec));
     }
 
+    let mut entries: Vec<&HistoryEntryDto> = date_dto_vec
+        .iter()
+        .flat_map(|(_, entries)| entries)
+        .collect();
+    entries.sort_by_key(|entry| (entry.activation.activated_at, entry.activation_id));
+    let mut store_paths: Vec<String> = entries
+        .iter()
+        .map(|entry| entry.activation.store_path.clone())
+        .collect();
+    store_paths.sort();
+    store_paths.dedup();
+    let with_manifest = package_service.with_manifest(&store_paths).await?;
+    let store_path_changes = store_path_changes(&entries, &with_manifest);
+    ctx.insert("store_path_changes", &store_path_changes);
+    let revisions: Vec<(i64, &String)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let revision = entry.activation.revision.as_ref()?;
+            Some((entry.activation_id, &revision.commit_hash))
+        })
+        .collect();
+    let commit_changes = commit_changes(&entries);
+    ctx.insert("commit_changes", &commit_changes);
+    if let Some(git_repository) = &git_repository {
+        let mut commit_hashes: Vec<String> = revisions
+            .iter()
+            .map(|(_, commit_hash)| commit_hash.to_string())
+            .collect();
+        commit_hashes.sort();
+        commit_hashes.dedup();
+        ctx.insert("commits", &git_repository.commits(commit_hashes).await);
+        ctx.insert(
+            "commit_ranges",
+            &git_repository.ranges(commit_changes).await,
+        );
+    }
+
     let host_dto = CurrentHostDto::from(host.clone());
     let history_ctx = HistoryPageContext::new(host_dto, date_dto_vec);
 
     ctx.insert("title", format!("History for {}", host.hostname).as_str());
-    ctx.insert("repo_url", &server_config.repo_url);
+    ctx.insert("feed_url", &format!("/feed/hosts/{}", host.hostname));
     ctx.insert("history_ctx", &history_ctx);
+    let (host_annotations, activation_annotations): (Vec<_>, Vec<_>) = annotation_service
+        .get_by_hostname(&host.hostname)
+        .await?
+        .into_iter()
+        .partition(|annotation| annotation.activation_id.is_none());
+    ctx.insert("host_annotations", &host_annotations);
+    ctx.insert("activation_annotations", &activation_annotations);
     let output = tera.render("history.html.tera", &ctx).unwrap();
     Ok(Html(output))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::dto::{activation::ActivationDto, revision::RevisionDto};
+    use chrono::{TimeZone, Utc};
+
+    fn entry(
+        activation_id: i64,
+        activation_type: &str,
+        commit_hash: Option<&str>,
+    ) -> HistoryEntryDto {
+        HistoryEntryDto {
+            activation_id,
+            activation: ActivationDto {
+                activated_at: Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap()
+                    + chrono::Duration::minutes(activation_id),
+                username: "root".to_string(),
+                store_path: format!("/nix/store/{}", commit_hash.unwrap_or("local")),
+                activation_type: activation_type.to_string(),
+                revision: commit_hash.map(|commit_hash| RevisionDto {
+                    commit_hash: commit_hash.to_string(),
+                    branch: "master".to_string(),
+                }),
+            },
+            is_rollback: false,
+        }
+    }
+
+    #[test]
+    fn test_commit_changes_between_deploys() {
+        let entries = [
+            entry(1, "switch", Some("a")),
+            entry(2, "dry-activate", Some("b")),
+            entry(3, "switch", Some("a")),
+            entry(4, "test", Some("c")),
+            entry(5, "switch", None),
+            entry(6, "boot", Some("d")),
+        ];
+        let entries: Vec<&HistoryEntryDto> = entries.iter().collect();
+        let changes: Vec<(i64, String, String)> = commit_changes(&entries)
+            .into_iter()
+            .map(|change| (change.activation_id, change.from, change.to))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (4, "a".to_string(), "c".to_string()),
+                (6, "c".to_string(), "d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_store_path_changes_between_deploys() {
+        let entries = [
+            entry(1, "switch", Some("a")),
+            entry(2, "dry-activate", Some("b")),
+            entry(3, "switch", Some("a")),
+            entry(4, "test", Some("c")),
+            entry(5, "switch", None),
+            entry(6, "boot", Some("d")),
+        ];
+        let entries: Vec<&HistoryEntryDto> = entries.iter().collect();
+        let with_manifest: HashSet<String> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|commit_hash| format!("/nix/store/{commit_hash}"))
+            .collect();
+        let changes: Vec<(i64, i64)> = store_path_changes(&entries, &with_manifest)
+            .into_iter()
+            .map(|change| (change.previous_activation_id, change.activation_id))
+            .collect();
+        // the locally built system of 5 has no manifest
+        assert_eq!(changes, vec![(3, 4)]);
+    }
+}
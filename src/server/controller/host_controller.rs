@@ -1,8 +1,16 @@
-use axum::{Json, extract::State};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
 
 use crate::{
-    server::ServerState,
-    shared::{dto::host::CurrentHostDto, model::host::HostModel},
+    server::{ServerState, custom_error::RetError, host_query::parse_host_query},
+    shared::{
+        dto::host::{CurrentHostDto, HostStateDto},
+        model::host::HostModel,
+    },
 };
 
 #[axum::debug_handler]
@@ -22,3 +30,43 @@ pub(crate) async fn create_hosts(
 
     Ok(num_inserted.to_string())
 }
+
+/// Retired hosts are left out of the rollout and counted apart in the metrics.
+#[axum::debug_handler]
+pub(crate) async fn retire_host(
+    State(ServerState { host_service, .. }): State<ServerState>,
+    Path(hostname): Path<String>,
+) -> axum::response::Result<StatusCode, RetError> {
+    host_service.set_retired(&hostname, true).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+pub(crate) async fn unretire_host(
+    State(ServerState { host_service, .. }): State<ServerState>,
+    Path(hostname): Path<String>,
+) -> axum::response::Result<StatusCode, RetError> {
+    host_service.set_retired(&hostname, false).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostsQuery {
+    q: Option<String>,
+    sort: Option<String>,
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_hosts(
+    State(ServerState { host_service, .. }): State<ServerState>,
+    Query(params): Query<HostsQuery>,
+) -> axum::response::Result<Json<Vec<HostStateDto>>, RetError> {
+    let (query, sort) = parse_host_query(params.q.as_deref(), params.sort.as_deref())?;
+    let hosts = host_service
+        .get_matching(&query, &sort)
+        .await?
+        .into_iter()
+        .map(HostStateDto::from)
+        .collect();
+    Ok(Json(hosts))
+}
@@ -0,0 +1,34 @@
+use axum::{
+    extract::State,
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::IntoResponse,
+};
+
+use crate::server::{ServerState, custom_error::RetError};
+
+#[axum::debug_handler]
+pub(crate) async fn render_metrics(
+    State(ServerState {
+        host_service,
+        desired_revision_service,
+        metrics,
+        ..
+    }): State<ServerState>,
+) -> axum::response::Result<impl IntoResponse, RetError> {
+    let rows = host_service.get_host_metrics().await?;
+    let hosts = host_service.get_all_with_state().await?;
+    let compliance = desired_revision_service.compliance(&hosts).await?;
+    let response = match metrics.encode(&rows, &compliance) {
+        Ok(body) => (
+            StatusCode::OK,
+            [(
+                CONTENT_TYPE,
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+            )],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+    Ok(response)
+}
@@ -1,6 +1,17 @@
+pub(crate) mod activity;
+pub(crate) mod annotation;
+pub(crate) mod desired_revision;
+pub(crate) mod events;
+pub(crate) mod export;
+pub(crate) mod feed;
 pub(crate) mod frontpage;
 pub(crate) mod history;
+pub(crate) mod package;
+pub(crate) mod report;
+pub(crate) mod rollout;
+pub(crate) mod store_path;
 
 pub(crate) mod activation_controller;
 pub(crate) mod host_controller;
+pub(crate) mod metrics_controller;
 pub(crate) mod nix_git_link_controller;
@@ -6,13 +6,16 @@ use axum::{Json, extract::State, response::IntoResponse};
 pub(crate) async fn create_links(
     State(ServerState {
         nix_git_link_service,
+        metrics,
         ..
     }): State<ServerState>,
     Json(nix_git_link): Json<Vec<NixGitLinkDto>>,
 ) -> axum::response::Result<impl IntoResponse> {
     tracing::info!(count = nix_git_link.len(), "received nix/git relationships");
 
+    let received = nix_git_link.len();
     let i = nix_git_link_service.create_many(nix_git_link).await?;
+    metrics.observe_links(received, i);
     tracing::info!(inserted = i, "inserted nix/git relationships");
     Ok((StatusCode::CREATED, format!("{i} links created")))
 }
@@ -0,0 +1,66 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::package::{PackageDiffDto, PackageManifestDto},
+};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse},
+};
+use serde::Deserialize;
+use tera::Context;
+
+/// Activation ids, as on GET /api/hosts/{hostname}/activations.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageDiffQuery {
+    from: i64,
+    to: i64,
+}
+
+#[axum::debug_handler]
+pub(crate) async fn upload_packages(
+    State(ServerState {
+        package_service, ..
+    }): State<ServerState>,
+    Json(manifest): Json<PackageManifestDto>,
+) -> axum::response::Result<impl IntoResponse, RetError> {
+    let store_path = manifest.store_path.clone();
+    let i = package_service.upload(manifest).await?;
+    tracing::info!(store_path, packages = i, "stored package manifest");
+    Ok((StatusCode::CREATED, format!("{i} packages stored")))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_package_diff(
+    State(ServerState {
+        package_service, ..
+    }): State<ServerState>,
+    Query(PackageDiffQuery { from, to }): Query<PackageDiffQuery>,
+) -> axum::response::Result<Json<PackageDiffDto>, RetError> {
+    let diff = package_service
+        .diff(from, to)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    Ok(Json(diff))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn render_package_diff_page(
+    State(ServerState {
+        tera,
+        package_service,
+        ..
+    }): State<ServerState>,
+    Query(PackageDiffQuery { from, to }): Query<PackageDiffQuery>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let diff = package_service
+        .diff(from, to)
+        .await?
+        .ok_or(RetError::NotFound)?;
+
+    let mut ctx = Context::new();
+    ctx.insert("title", "Package diff");
+    ctx.insert("diff_ctx", &diff);
+    Ok(Html(tera.render("package_diff.html.tera", &ctx).unwrap()))
+}
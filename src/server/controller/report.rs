@@ -0,0 +1,54 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::activation::ActivationDto,
+};
+use axum::{
+    extract::{Query, State},
+    response::Html,
+};
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+const PER_PAGE: u32 = 100;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportQuery {
+    page: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UnknownProvenanceRow {
+    hostname: String,
+    activation: ActivationDto,
+}
+
+#[axum::debug_handler]
+pub async fn render_unknown_provenance_report(
+    State(ServerState {
+        tera,
+        activation_log_service,
+        ..
+    }): State<ServerState>,
+    Query(params): Query<ReportQuery>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let (activations, has_more) = activation_log_service
+        .get_unknown_provenance(page, PER_PAGE)
+        .await?;
+    let rows: Vec<UnknownProvenanceRow> = activations
+        .into_iter()
+        .map(|activation| UnknownProvenanceRow {
+            hostname: activation.core.hostname.clone(),
+            activation: activation.into(),
+        })
+        .collect();
+
+    let mut ctx = Context::new();
+    ctx.insert("title", "unknown provenance");
+    ctx.insert("rows", &rows);
+    ctx.insert("page", &page);
+    ctx.insert("has_more", &has_more);
+    Ok(Html(
+        tera.render("unknown_provenance.html.tera", &ctx).unwrap(),
+    ))
+}
@@ -0,0 +1,100 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::rollout::RolloutDto,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::Html,
+};
+use serde::Deserialize;
+use tera::Context;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RolloutQuery {
+    grouping_key: Option<String>,
+    /// Comma separated store path keys, used when no commit is given.
+    store_paths: Option<String>,
+}
+
+async fn rollout(
+    server_state: &ServerState,
+    commit: Option<&str>,
+    params: &RolloutQuery,
+) -> Result<RolloutDto, RetError> {
+    let grouping_key = params
+        .grouping_key
+        .as_ref()
+        .or(server_state.server_config.default_grouping_key())
+        .cloned();
+    let rollout = match commit {
+        Some(commit) => {
+            server_state
+                .rollout_service
+                .get_commit_rollout(commit, grouping_key)
+                .await?
+        }
+        None => {
+            let keys: Vec<&str> = params
+                .store_paths
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .collect();
+            server_state
+                .rollout_service
+                .get_store_paths_rollout(&keys, grouping_key)
+                .await?
+        }
+    };
+    Ok(rollout.ok_or(RetError::NotFound)?.into())
+}
+
+fn render(server_state: &ServerState, rollout: &RolloutDto) -> Html<String> {
+    let title = match &rollout.commit_hash {
+        Some(commit_hash) => format!("rollout of {commit_hash}"),
+        None => "rollout of store paths".to_string(),
+    };
+    let mut ctx = Context::new();
+    ctx.insert("title", &title);
+    ctx.insert("rollout_ctx", rollout);
+    Html(server_state.tera.render("rollout.html.tera", &ctx).unwrap())
+}
+
+#[axum::debug_handler]
+pub async fn render_commit_rollout_page(
+    State(server_state): State<ServerState>,
+    Path(commit): Path<String>,
+    Query(params): Query<RolloutQuery>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let rollout = rollout(&server_state, Some(&commit), &params).await?;
+    Ok(render(&server_state, &rollout))
+}
+
+#[axum::debug_handler]
+pub async fn render_store_paths_rollout_page(
+    State(server_state): State<ServerState>,
+    Query(params): Query<RolloutQuery>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let rollout = rollout(&server_state, None, &params).await?;
+    Ok(render(&server_state, &rollout))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_commit_rollout(
+    State(server_state): State<ServerState>,
+    Path(commit): Path<String>,
+    Query(params): Query<RolloutQuery>,
+) -> axum::response::Result<Json<RolloutDto>, RetError> {
+    Ok(Json(rollout(&server_state, Some(&commit), &params).await?))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_store_paths_rollout(
+    State(server_state): State<ServerState>,
+    Query(params): Query<RolloutQuery>,
+) -> axum::response::Result<Json<RolloutDto>, RetError> {
+    Ok(Json(rollout(&server_state, None, &params).await?))
+}
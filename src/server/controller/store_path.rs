@@ -0,0 +1,45 @@
+use crate::{
+    server::{ServerState, custom_error::RetError},
+    shared::dto::store_path::StorePathDto,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::Html,
+};
+use tera::Context;
+
+#[axum::debug_handler]
+pub async fn render_store_path_page(
+    State(ServerState {
+        tera,
+        store_path_service,
+        ..
+    }): State<ServerState>,
+    Path(key): Path<String>,
+) -> axum::response::Result<Html<String>, RetError> {
+    let detail = store_path_service
+        .get_detail(&key)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    let store_path = StorePathDto::from(detail);
+
+    let mut ctx = Context::new();
+    ctx.insert("title", &store_path.store_path);
+    ctx.insert("store_path_ctx", &store_path);
+    Ok(Html(tera.render("store_path.html.tera", &ctx).unwrap()))
+}
+
+#[axum::debug_handler]
+pub(crate) async fn get_store_path(
+    State(ServerState {
+        store_path_service, ..
+    }): State<ServerState>,
+    Path(key): Path<String>,
+) -> axum::response::Result<Json<StorePathDto>, RetError> {
+    let detail = store_path_service
+        .get_detail(&key)
+        .await?
+        .ok_or(RetError::NotFound)?;
+    Ok(Json(detail.into()))
+}
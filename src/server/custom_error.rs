@@ -1,12 +1,18 @@
 use axum::response::IntoResponse;
 use reqwest::StatusCode;
 
+use crate::server::host_query::QueryError;
+
 #[derive(Debug, thiserror::Error)]
 pub(super) enum RetError {
     #[error("Database error: {0}")]
     DbError(#[from] sqlx::Error),
     #[error("Not Found")]
     NotFound,
+    #[error("Invalid query: {0}")]
+    InvalidQuery(#[from] QueryError),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
 }
 
 impl IntoResponse for RetError {
@@ -20,6 +26,10 @@ impl IntoResponse for RetError {
                 "The thing you were looking for could not be found".to_string(),
             )
                 .into_response(),
+            RetError::InvalidQuery(err) => {
+                (StatusCode::BAD_REQUEST, format!("Invalid query: {err}")).into_response()
+            }
+            RetError::BadRequest(message) => (StatusCode::BAD_REQUEST, message).into_response(),
         }
     }
 }
@@ -45,4 +55,16 @@ mod tests {
         let response = not_found_err.into_response();
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+    #[test]
+    fn test_ret_error_invalid_query() {
+        let err = RetError::InvalidQuery(QueryError::MissingValue("env:".to_string()));
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    #[test]
+    fn test_ret_error_bad_request() {
+        let err = RetError::BadRequest("body must not be empty".to_string());
+        let response = err.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,6 +1,33 @@
 pub fn activations_bulk() -> &'static str {
     "/api/activation/bulk"
 }
+pub fn hosts() -> &'static str {
+    "/api/hosts"
+}
+pub fn host_activations() -> &'static str {
+    "/api/hosts/{hostname}/activations"
+}
+pub fn retire_host() -> &'static str {
+    "/api/hosts/{hostname}/retire"
+}
+pub fn host_annotations() -> &'static str {
+    "/api/hosts/{hostname}/annotations"
+}
+pub fn annotation() -> &'static str {
+    "/api/annotations/{annotation_id}"
+}
+pub fn expire_annotation() -> &'static str {
+    "/api/annotations/{annotation_id}/expire"
+}
+pub fn desired_revisions() -> &'static str {
+    "/api/desired"
+}
+pub fn group_desired_revision() -> &'static str {
+    "/api/desired/{grouping_key}/{group_value}"
+}
+pub fn compliance() -> &'static str {
+    "/api/compliance"
+}
 pub fn hosts_bulk() -> &'static str {
     "/api/hosts/bulk"
 }
@@ -14,6 +41,66 @@ pub fn nix_git_link() -> &'static str {
 pub fn nix_git_link_bulk() -> &'static str {
     "/api/link/bulk"
 }
+pub fn activity() -> &'static str {
+    "/activity"
+}
+pub fn activity_api() -> &'static str {
+    "/api/activity"
+}
+pub fn frontpage_export() -> &'static str {
+    "/api/export/hosts"
+}
+pub fn history_export() -> &'static str {
+    "/api/export/hosts/{hostname}/history"
+}
+pub fn fleet_feed() -> &'static str {
+    "/feed"
+}
+pub fn host_feed() -> &'static str {
+    "/feed/hosts/{hostname}"
+}
+pub fn group_feed() -> &'static str {
+    "/feed/groups/{key}/{value}"
+}
+pub fn host_events() -> &'static str {
+    "/api/events"
+}
+pub fn host_row() -> &'static str {
+    "/hosts/{hostname}/row"
+}
+pub fn rollout() -> &'static str {
+    "/rollout"
+}
+pub fn commit_rollout() -> &'static str {
+    "/rollout/{commit}"
+}
+pub fn rollout_api() -> &'static str {
+    "/api/rollouts"
+}
+pub fn commit_rollout_api() -> &'static str {
+    "/api/rollouts/{commit}"
+}
+pub fn store_path() -> &'static str {
+    "/store-path/{key}"
+}
+pub fn store_path_api() -> &'static str {
+    "/api/store-paths/{key}"
+}
+pub fn packages() -> &'static str {
+    "/api/packages"
+}
+pub fn package_diff() -> &'static str {
+    "/diff"
+}
+pub fn package_diff_api() -> &'static str {
+    "/api/diff"
+}
+pub fn unknown_provenance_report() -> &'static str {
+    "/report/unknown-provenance"
+}
+pub fn metrics() -> &'static str {
+    "/metrics"
+}
 pub fn history() -> &'static str {
     "/{hostname}"
 }
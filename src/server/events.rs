@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use sqlx::{Pool, Postgres, postgres::PgListener};
+use tokio::sync::broadcast;
+
+use crate::{server::service::host_service::HostService, shared::dto::host::HostStateDto};
+
+/// Postgres channel the hostnames of new activations are sent on.
+pub(crate) const HOST_ACTIVATIONS_CHANNEL: &str = "hostmap_host_activations";
+
+/// Host states sent to subscribers that are behind are dropped.
+const CAPACITY: usize = 256;
+
+/// The new state of hosts that got activations, from this or any other server
+/// sharing the database.
+#[derive(Debug, Clone)]
+pub(crate) struct HostEvents {
+    sender: broadcast::Sender<HostStateDto>,
+}
+
+impl HostEvents {
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<HostStateDto> {
+        self.sender.subscribe()
+    }
+
+    /// Listens for activation notifications in the background.
+    pub(crate) fn spawn_listener(&self, pool: Pool<Postgres>, host_service: HostService) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = listen(&pool, &host_service, &sender).await {
+                    tracing::error!(error = %err, "activation listener failed, retrying");
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+}
+
+async fn listen(
+    pool: &Pool<Postgres>,
+    host_service: &HostService,
+    sender: &broadcast::Sender<HostStateDto>,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(HOST_ACTIVATIONS_CHANNEL).await?;
+    tracing::info!(
+        channel = HOST_ACTIVATIONS_CHANNEL,
+        "listening for activations"
+    );
+    loop {
+        let notification = listener.recv().await?;
+        let hostname = notification.payload();
+        match host_service.get_with_state(hostname).await {
+            Ok(Some(host)) => {
+                // fails only when nobody is subscribed
+                let _ = sender.send(host.into());
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(host = hostname, error = %err, "could not load host state");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{
+        branch_precedence::BranchPrecedence,
+        repository::{
+            activation_repository::ActivationRepository,
+            fixtures::{activate, insert_hosts},
+        },
+    };
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_notification_becomes_host_event(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b"]).await;
+        let events = HostEvents::new();
+        let mut receiver = events.subscribe();
+        events.spawn_listener(
+            pool.clone(),
+            HostService::new(pool.clone(), BranchPrecedence::new(&[])),
+        );
+        // the listener has to be listening before the notification is sent
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let ids = activate(&pool, "b", &[(0, "/nix/store/b", "switch")]).await;
+        let mut tx = pool.begin().await.unwrap();
+        ActivationRepository::notify_hosts(&mut tx, HOST_ACTIVATIONS_CHANNEL, &ids)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let host = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("no event for the notification")
+            .unwrap();
+        assert_eq!(host.host.hostname, "b");
+        assert_eq!(
+            host.host.logs.map(|activation| activation.store_path),
+            Some("/nix/store/b".to_string())
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Spreadsheet friendly exports of the frontpage and the history of a host.
+//! Timestamps are RFC 3339 in the configured time zone.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    server::grouping::GroupingKey,
+    shared::dto::{activation::HistoryEntryDto, host::HostStateDto},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+fn format_time(time: DateTime<Utc>, tz: Tz) -> String {
+    time.with_timezone(&tz)
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct GroupValue {
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HostExportRow {
+    /// The group of the host for each grouping key, outermost first.
+    pub groups: Vec<GroupValue>,
+    pub hostname: String,
+    /// The configured columns only.
+    pub metadata: BTreeMap<String, String>,
+    pub activated_at: Option<String>,
+    pub username: Option<String>,
+    pub activation_type: Option<String>,
+    pub store_path: Option<String>,
+    pub branch: Option<String>,
+    pub commit_hash: Option<String>,
+    pub next_boot_store_path: Option<String>,
+    pub reboot_pending: bool,
+    pub rollbacks: i64,
+}
+
+/// The hosts of the frontpage, in the order of their groups.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct FrontpageExport {
+    pub time_zone: String,
+    pub grouping_keys: Vec<String>,
+    pub columns: Vec<String>,
+    pub hosts: Vec<HostExportRow>,
+}
+
+impl FrontpageExport {
+    pub(crate) fn new(
+        hosts: Vec<HostStateDto>,
+        grouping_keys: &[String],
+        parsed_keys: &[GroupingKey],
+        columns: &[String],
+        tz: Tz,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut grouped: Vec<(Vec<(usize, String)>, HostStateDto)> = hosts
+            .into_iter()
+            .map(|host| {
+                let groups = parsed_keys
+                    .iter()
+                    .map(|key| key.group_of(&host, now))
+                    .collect();
+                (groups, host)
+            })
+            .collect();
+        // stable, so hosts keep their order within a group like on the page
+        grouped.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let hosts = grouped
+            .into_iter()
+            .map(|(groups, host)| {
+                let running = host.host.logs.as_ref();
+                let revision = running.and_then(|activation| activation.revision.as_ref());
+                HostExportRow {
+                    groups: grouping_keys
+                        .iter()
+                        .zip(groups)
+                        .map(|(key, (_, name))| GroupValue {
+                            key: key.clone(),
+                            name,
+                        })
+                        .collect(),
+                    metadata: columns
+                        .iter()
+                        .filter_map(|column| {
+                            let value = host.host.metadata.get(column)?;
+                            Some((column.clone(), value.clone()))
+                        })
+                        .collect(),
+                    activated_at: running
+                        .map(|activation| format_time(activation.activated_at, tz)),
+                    username: running.map(|activation| activation.username.clone()),
+                    activation_type: running.map(|activation| activation.activation_type.clone()),
+                    store_path: running.map(|activation| activation.store_path.clone()),
+                    branch: revision.map(|revision| revision.branch.clone()),
+                    commit_hash: revision.map(|revision| revision.commit_hash.clone()),
+                    next_boot_store_path: host
+                        .next_boot
+                        .as_ref()
+                        .map(|activation| activation.store_path.clone()),
+                    reboot_pending: host.reboot_pending,
+                    rollbacks: host.rollbacks,
+                    hostname: host.host.hostname,
+                }
+            })
+            .collect();
+
+        Self {
+            time_zone: tz.to_string(),
+            grouping_keys: grouping_keys.to_vec(),
+            columns: columns.to_vec(),
+            hosts,
+        }
+    }
+
+    /// One column per grouping key and configured column, then the running system.
+    pub(crate) fn to_csv(&self) -> String {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let header = self
+            .grouping_keys
+            .iter()
+            .map(|key| format!("group {key}"))
+            .chain(std::iter::once("hostname".to_string()))
+            .chain(self.columns.iter().cloned())
+            .chain(
+                [
+                    "activated_at",
+                    "username",
+                    "activation_type",
+                    "store_path",
+                    "branch",
+                    "commit_hash",
+                    "next_boot_store_path",
+                    "reboot_pending",
+                    "rollbacks",
+                ]
+                .map(str::to_string),
+            );
+        writer.write_record(header).expect("writing csv to memory");
+        for host in &self.hosts {
+            let optional = [
+                &host.activated_at,
+                &host.username,
+                &host.activation_type,
+                &host.store_path,
+                &host.branch,
+                &host.commit_hash,
+                &host.next_boot_store_path,
+            ];
+            let record = host
+                .groups
+                .iter()
+                .map(|group| group.name.clone())
+                .chain(std::iter::once(host.hostname.clone()))
+                .chain(
+                    self.columns
+                        .iter()
+                        .map(|column| host.metadata.get(column).cloned().unwrap_or_default()),
+                )
+                .chain(
+                    optional
+                        .into_iter()
+                        .map(|value| value.clone().unwrap_or_default()),
+                )
+                .chain([host.reboot_pending.to_string(), host.rollbacks.to_string()]);
+            writer.write_record(record).expect("writing csv to memory");
+        }
+        csv_string(writer)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HistoryExportRow {
+    /// The day in the configured time zone, as the history page groups them.
+    pub date: NaiveDate,
+    pub activated_at: String,
+    pub username: String,
+    pub activation_type: String,
+    pub store_path: String,
+    pub branch: Option<String>,
+    pub commit_hash: Option<String>,
+    pub is_rollback: bool,
+}
+
+/// The activations of a host, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct HistoryExport {
+    pub time_zone: String,
+    pub hostname: String,
+    pub activations: Vec<HistoryExportRow>,
+}
+
+impl HistoryExport {
+    pub(crate) fn new(
+        hostname: String,
+        activations_by_date: Vec<(NaiveDate, Vec<HistoryEntryDto>)>,
+        tz: Tz,
+    ) -> Self {
+        let mut activations_by_date = activations_by_date;
+        // the history page lists the activations of a day newest first
+        activations_by_date.sort_by_key(|(date, _)| *date);
+        for (_, entries) in &mut activations_by_date {
+            entries.sort_by_key(|entry| (entry.activation.activated_at, entry.activation_id));
+        }
+        let activations = activations_by_date
+            .into_iter()
+            .flat_map(|(date, entries)| {
+                entries.into_iter().map(move |entry| {
+                    let activation = entry.activation;
+                    let revision = activation.revision;
+                    HistoryExportRow {
+                        date,
+                        activated_at: format_time(activation.activated_at, tz),
+                        username: activation.username,
+                        activation_type: activation.activation_type,
+                        store_path: activation.store_path,
+                        branch: revision.as_ref().map(|revision| revision.branch.clone()),
+                        commit_hash: revision.map(|revision| revision.commit_hash),
+                        is_rollback: entry.is_rollback,
+                    }
+                })
+            })
+            .collect();
+        Self {
+            time_zone: tz.to_string(),
+            hostname,
+            activations,
+        }
+    }
+
+    /// The header is written even when there are no activations.
+    pub(crate) fn to_csv(&self) -> String {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record([
+                "date",
+                "activated_at",
+                "username",
+                "activation_type",
+                "store_path",
+                "branch",
+                "commit_hash",
+                "is_rollback",
+            ])
+            .expect("writing csv to memory");
+        for row in &self.activations {
+            writer
+                .write_record([
+                    row.date.to_string(),
+                    row.activated_at.clone(),
+                    row.username.clone(),
+                    row.activation_type.clone(),
+                    row.store_path.clone(),
+                    row.branch.clone().unwrap_or_default(),
+                    row.commit_hash.clone().unwrap_or_default(),
+                    row.is_rollback.to_string(),
+                ])
+                .expect("writing csv to memory");
+        }
+        csv_string(writer)
+    }
+}
+
+fn csv_string(writer: csv::Writer<Vec<u8>>) -> String {
+    let bytes = writer.into_inner().expect("flushing csv to memory");
+    String::from_utf8(bytes).expect("csv of strings is utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::{
+        dto::activation::ActivationDto,
+        model::{
+            activation::{Activation, ActivationCore},
+            host::{HostModel, HostWithState},
+            revision::RevisionModel,
+        },
+    };
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn host(hostname: &str, env: &str, commit: Option<&str>) -> HostStateDto {
+        HostStateDto::from(HostWithState {
+            host: HostModel {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::from([("env".to_string(), env.to_string())]),
+            },
+            running: Some(Activation {
+                id: 1,
+                core: ActivationCore {
+                    activated_at: Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap(),
+                    username: "root".to_string(),
+                    store_path: format!("/nix/store/{hostname}"),
+                    activation_type: "switch".to_string(),
+                    hostname: hostname.to_string(),
+                    revision: commit.map(|commit| RevisionModel {
+                        commit_hash: commit.to_string(),
+                        branch: "master".to_string(),
+                    }),
+                },
+            }),
+            next_boot: None,
+            latest_test: None,
+            rollbacks: 0,
+            latest_build: None,
+            running_inputs: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_frontpage_csv_follows_grouping() {
+        let keys = vec!["env".to_string()];
+        let parsed_keys = vec![GroupingKey::parse("env").unwrap()];
+        let export = FrontpageExport::new(
+            vec![
+                host("b", "staging", None),
+                host("c", "prod", Some("abc")),
+                host("a", "prod", Some("abc")),
+            ],
+            &keys,
+            &parsed_keys,
+            &["env".to_string(), "rack".to_string()],
+            chrono_tz::Europe::Copenhagen,
+            Utc::now(),
+        );
+        let hostnames: Vec<&str> = export.hosts.iter().map(|h| h.hostname.as_str()).collect();
+        assert_eq!(hostnames, vec!["c", "a", "b"]);
+
+        let csv = export.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "group env,hostname,env,rack,activated_at,username,activation_type,store_path,\
+             branch,commit_hash,next_boot_store_path,reboot_pending,rollbacks"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "prod,c,prod,,2026-01-05T13:00:00+01:00,root,switch,/nix/store/c,master,abc,,false,0"
+        );
+    }
+
+    #[test]
+    fn test_history_export_is_chronological() {
+        let entry = |activation_id, hour| HistoryEntryDto {
+            activation_id,
+            activation: ActivationDto {
+                activated_at: Utc.with_ymd_and_hms(2026, 1, 5, hour, 0, 0).unwrap()
+                    + chrono::Duration::days(activation_id / 10),
+                username: "root".to_string(),
+                store_path: format!("/nix/store/{activation_id}"),
+                activation_type: "switch".to_string(),
+                revision: None,
+            },
+            is_rollback: false,
+        };
+        let day = |day| NaiveDate::from_ymd_opt(2026, 1, day).unwrap();
+        // as the history page groups them, each day newest first
+        let export = HistoryExport::new(
+            "a".to_string(),
+            vec![
+                (day(6), vec![entry(12, 14), entry(11, 9)]),
+                (day(5), vec![entry(2, 14), entry(1, 9)]),
+            ],
+            chrono_tz::UTC,
+        );
+        let store_paths: Vec<&str> = export
+            .activations
+            .iter()
+            .map(|row| row.store_path.as_str())
+            .collect();
+        assert_eq!(
+            store_paths,
+            vec![
+                "/nix/store/1",
+                "/nix/store/2",
+                "/nix/store/11",
+                "/nix/store/12"
+            ]
+        );
+        let csv = export.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "date,activated_at,username,activation_type,store_path,branch,commit_hash,is_rollback"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "2026-01-05,2026-01-05T09:00:00Z,root,switch,/nix/store/1,,,false"
+        );
+    }
+
+    #[test]
+    fn test_empty_history_csv_has_a_header() {
+        let export = HistoryExport::new("a".to_string(), Vec::new(), chrono_tz::UTC);
+        assert_eq!(
+            export.to_csv(),
+            "date,activated_at,username,activation_type,store_path,branch,commit_hash,is_rollback\n"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Links to the forge hosting the repo, built from URL templates so any URL
+//! shape works: GitHub, GitLab's `/-/commit`, Gitea, cgit's `?id=`. The links
+//! are the tera functions `commit_url(commit)`, `compare_url(from, to)` and
+//! `branch_url(branch)`, which give an empty string when not configured.
+
+use std::collections::HashMap;
+
+use tera::{Tera, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("url template `{template}` is missing `{placeholder}`")]
+pub(crate) struct TemplateError {
+    template: String,
+    placeholder: &'static str,
+}
+
+/// A URL with placeholders like `{commit}`, replaced by percent-encoded values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UrlTemplate(String);
+
+impl UrlTemplate {
+    fn parse(template: String, placeholders: &[&'static str]) -> Result<Self, TemplateError> {
+        for placeholder in placeholders {
+            if !template.contains(placeholder) {
+                return Err(TemplateError {
+                    template,
+                    placeholder,
+                });
+            }
+        }
+        Ok(Self(template))
+    }
+
+    fn expand(&self, values: &[(&str, &str)]) -> String {
+        values
+            .iter()
+            .fold(self.0.clone(), |url, (placeholder, value)| {
+                url.replace(placeholder, &encode(value))
+            })
+    }
+}
+
+/// Percent-encodes all but unreserved characters and `/`, which branch names
+/// keep as path segments on every forge.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ForgeLinks {
+    commit: Option<UrlTemplate>,
+    compare: Option<UrlTemplate>,
+    branch: Option<UrlTemplate>,
+}
+
+impl ForgeLinks {
+    /// `repo_url` is the old prefix the commit hash was appended to, used when
+    /// there is no commit template.
+    pub(crate) fn new(
+        commit: Option<String>,
+        compare: Option<String>,
+        branch: Option<String>,
+        repo_url: Option<String>,
+    ) -> Result<Self, TemplateError> {
+        let commit = commit.or_else(|| {
+            repo_url.map(|repo_url| format!("{}/{{commit}}", repo_url.trim_end_matches('/')))
+        });
+        Ok(Self {
+            commit: commit
+                .map(|template| UrlTemplate::parse(template, &["{commit}"]))
+                .transpose()?,
+            compare: compare
+                .map(|template| UrlTemplate::parse(template, &["{from}", "{to}"]))
+                .transpose()?,
+            branch: branch
+                .map(|template| UrlTemplate::parse(template, &["{branch}"]))
+                .transpose()?,
+        })
+    }
+
+    pub(crate) fn commit(&self, commit: &str) -> Option<String> {
+        Some(self.commit.as_ref()?.expand(&[("{commit}", commit)]))
+    }
+
+    pub(crate) fn compare(&self, from: &str, to: &str) -> Option<String> {
+        Some(
+            self.compare
+                .as_ref()?
+                .expand(&[("{from}", from), ("{to}", to)]),
+        )
+    }
+
+    pub(crate) fn branch(&self, branch: &str) -> Option<String> {
+        Some(self.branch.as_ref()?.expand(&[("{branch}", branch)]))
+    }
+
+    pub(crate) fn register(&self, tera: &mut Tera) {
+        let links = self.clone();
+        tera.register_function("commit_url", move |args: &HashMap<String, Value>| {
+            let commit = string_arg("commit_url", "commit", args)?;
+            Ok(Value::String(links.commit(&commit).unwrap_or_default()))
+        });
+        let links = self.clone();
+        tera.register_function("compare_url", move |args: &HashMap<String, Value>| {
+            let from = string_arg("compare_url", "from", args)?;
+            let to = string_arg("compare_url", "to", args)?;
+            Ok(Value::String(links.compare(&from, &to).unwrap_or_default()))
+        });
+        let links = self.clone();
+        tera.register_function("branch_url", move |args: &HashMap<String, Value>| {
+            let branch = string_arg("branch_url", "branch", args)?;
+            Ok(Value::String(links.branch(&branch).unwrap_or_default()))
+        });
+    }
+}
+
+fn string_arg(function: &str, name: &str, args: &HashMap<String, Value>) -> tera::Result<String> {
+    match args.get(name) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(value) => {
+            Err(format!("`{function}` expects `{name}` to be a string, got {value}").into())
+        }
+        None => Err(format!("`{function}` is missing the `{name}` argument").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forge_links() {
+        let links = ForgeLinks::new(
+            Some("https://cgit.example.com/deploy/commit/?id={commit}".to_string()),
+            Some("https://gitea.example.com/o/r/compare/{from}...{to}".to_string()),
+            Some("https://gitea.example.com/o/r/src/branch/{branch}".to_string()),
+            Some("https://ignored.example.com".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            links.commit("abc").unwrap(),
+            "https://cgit.example.com/deploy/commit/?id=abc"
+        );
+        assert_eq!(
+            links.compare("abc", "def").unwrap(),
+            "https://gitea.example.com/o/r/compare/abc...def"
+        );
+        assert_eq!(
+            links.branch("release/25.05 #2").unwrap(),
+            "https://gitea.example.com/o/r/src/branch/release/25.05%20%232"
+        );
+
+        let links = ForgeLinks::new(
+            None,
+            None,
+            None,
+            Some("https://gitlab.example.com/o/r/-/commit/".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            links.commit("abc").unwrap(),
+            "https://gitlab.example.com/o/r/-/commit/abc"
+        );
+        assert_eq!(links.compare("abc", "def"), None);
+
+        let err = ForgeLinks::new(None, Some("https://x/{from}".to_string()), None, None);
+        assert_eq!(err.unwrap_err().placeholder, "{to}");
+    }
+}
@@ -0,0 +1,297 @@
+//! Commit metadata and ancestry from a local checkout of the repository the
+//! systems are built from. The checkout is opened on every lookup, so commits
+//! fetched into it show up without restarting the server.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository, Sort};
+use serde::Serialize;
+
+/// Commits listed per side of a range, the rest are counted as truncated.
+const RANGE_LENGTH: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Ancestry {
+    pub branch: String,
+    /// Commits of the commit that are not on the branch.
+    pub ahead: usize,
+    /// Commits of the branch that are not in the commit.
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CommitInfo {
+    pub commit_hash: String,
+    /// First line of the message.
+    pub summary: String,
+    pub author: String,
+    pub committed_at: DateTime<Utc>,
+    /// Against the default branch, None in ranges or when it cannot be resolved.
+    pub ancestry: Option<Ancestry>,
+}
+
+/// The commits between the revision of an activation and that of the one before it.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CommitRange {
+    pub activation_id: i64,
+    /// Commits the activation brought in, newest first.
+    pub added: Vec<CommitInfo>,
+    /// Commits the activation went back on, e.g. in a rollback, newest first.
+    pub removed: Vec<CommitInfo>,
+    /// More commits than listed were added or removed.
+    pub truncated: bool,
+}
+
+/// A range to look up for an activation, from the commit before it to its own.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RangeQuery {
+    pub activation_id: i64,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GitRepository {
+    path: PathBuf,
+    /// Branch to count ahead and behind against, HEAD of the checkout if unset.
+    default_branch: Option<String>,
+}
+
+impl GitRepository {
+    pub(crate) fn new(path: PathBuf, default_branch: Option<String>) -> Self {
+        Self {
+            path,
+            default_branch,
+        }
+    }
+
+    /// The commits found in the checkout by hash, hashes it lacks are left out.
+    /// Lookups never fail a page, errors are logged and give no commits.
+    pub(crate) async fn commits(&self, commit_hashes: Vec<String>) -> HashMap<String, CommitInfo> {
+        let repository = self.clone();
+        let res =
+            tokio::task::spawn_blocking(move || repository.commits_blocking(&commit_hashes)).await;
+        match res {
+            Ok(Ok(commits)) => commits,
+            Ok(Err(err)) => {
+                tracing::warn!(error = %err, path = %self.path.display(), "could not read commits from git repository");
+                HashMap::new()
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "git lookup panicked");
+                HashMap::new()
+            }
+        }
+    }
+
+    /// The commits of every range, ranges with a commit the checkout lacks are left out.
+    pub(crate) async fn ranges(&self, queries: Vec<RangeQuery>) -> Vec<CommitRange> {
+        let repository = self.clone();
+        let res = tokio::task::spawn_blocking(move || repository.ranges_blocking(&queries)).await;
+        match res {
+            Ok(Ok(ranges)) => ranges,
+            Ok(Err(err)) => {
+                tracing::warn!(error = %err, path = %self.path.display(), "could not read commit ranges from git repository");
+                Vec::new()
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "git lookup panicked");
+                Vec::new()
+            }
+        }
+    }
+
+    fn open(&self) -> Result<Repository, git2::Error> {
+        Repository::open(&self.path)
+    }
+
+    /// The default branch name and its tip.
+    fn default_branch_tip(&self, repo: &Repository) -> Result<(String, Oid), git2::Error> {
+        match &self.default_branch {
+            Some(branch) => {
+                let tip = repo.revparse_single(branch)?.peel_to_commit()?.id();
+                Ok((branch.clone(), tip))
+            }
+            None => {
+                let head = repo.head()?;
+                let name = head.shorthand().unwrap_or("HEAD").to_string();
+                Ok((name, head.peel_to_commit()?.id()))
+            }
+        }
+    }
+
+    fn commits_blocking(
+        &self,
+        commit_hashes: &[String],
+    ) -> Result<HashMap<String, CommitInfo>, git2::Error> {
+        let repo = self.open()?;
+        let default_branch = self
+            .default_branch_tip(&repo)
+            .inspect_err(|err| tracing::warn!(error = %err, "could not resolve the default branch"))
+            .ok();
+        let mut commits = HashMap::new();
+        for commit_hash in commit_hashes {
+            let Some(oid) = find_commit(&repo, commit_hash) else {
+                continue;
+            };
+            let mut info = commit_info(&repo, oid)?;
+            if let Some((branch, tip)) = &default_branch {
+                let (ahead, behind) = repo.graph_ahead_behind(oid, *tip)?;
+                info.ancestry = Some(Ancestry {
+                    branch: branch.clone(),
+                    ahead,
+                    behind,
+                });
+            }
+            commits.insert(commit_hash.clone(), info);
+        }
+        Ok(commits)
+    }
+
+    fn ranges_blocking(&self, queries: &[RangeQuery]) -> Result<Vec<CommitRange>, git2::Error> {
+        let repo = self.open()?;
+        let mut ranges = Vec::new();
+        for query in queries {
+            let (Some(from), Some(to)) = (
+                find_commit(&repo, &query.from),
+                find_commit(&repo, &query.to),
+            ) else {
+                continue;
+            };
+            let (added, added_truncated) = commits_between(&repo, from, to)?;
+            let (removed, removed_truncated) = commits_between(&repo, to, from)?;
+            ranges.push(CommitRange {
+                activation_id: query.activation_id,
+                added,
+                removed,
+                truncated: added_truncated || removed_truncated,
+            });
+        }
+        Ok(ranges)
+    }
+}
+
+fn find_commit(repo: &Repository, commit_hash: &str) -> Option<Oid> {
+    let object = repo.revparse_single(commit_hash).ok()?;
+    Some(object.peel_to_commit().ok()?.id())
+}
+
+fn commit_info(repo: &Repository, oid: Oid) -> Result<CommitInfo, git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let committed_at =
+        DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or(DateTime::UNIX_EPOCH);
+    Ok(CommitInfo {
+        commit_hash: oid.to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        author: commit.author().name().unwrap_or_default().to_string(),
+        committed_at,
+        ancestry: None,
+    })
+}
+
+/// Commits reachable from `to` but not from `from`, newest first, and whether
+/// there were more than [`RANGE_LENGTH`].
+fn commits_between(
+    repo: &Repository,
+    from: Oid,
+    to: Oid,
+) -> Result<(Vec<CommitInfo>, bool), git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(to)?;
+    walk.hide(from)?;
+    let mut commits = Vec::new();
+    for oid in walk {
+        if commits.len() == RANGE_LENGTH {
+            return Ok((commits, true));
+        }
+        commits.push(commit_info(repo, oid?)?);
+    }
+    Ok((commits, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repository with master `a - b - c` and a branch `feature` of `b - d`.
+    fn repository() -> (PathBuf, Vec<String>) {
+        let path = std::env::temp_dir().join(format!("hostmap-git-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).unwrap();
+        let signature = git2::Signature::new(
+            "alice",
+            "alice@example.com",
+            &git2::Time::new(1_767_225_600, 0),
+        )
+        .unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let commit = |message: &str, parents: &[Oid]| {
+            let parents: Vec<_> = parents
+                .iter()
+                .map(|oid| repo.find_commit(*oid).unwrap())
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(None, &signature, &signature, message, &tree, &parents)
+                .unwrap()
+        };
+        let a = commit("a\n\nbody", &[]);
+        let b = commit("b", &[a]);
+        let c = commit("c", &[b]);
+        let d = commit("d", &[b]);
+        repo.branch("master", &repo.find_commit(c).unwrap(), true)
+            .unwrap();
+        repo.set_head("refs/heads/master").unwrap();
+        let hashes = [a, b, c, d].map(|oid| oid.to_string()).to_vec();
+        (path, hashes)
+    }
+
+    #[tokio::test]
+    async fn test_commits_and_ranges() {
+        let (path, hashes) = repository();
+        let [a, _b, c, d] = [0, 1, 2, 3].map(|i| hashes[i].clone());
+        let git = GitRepository::new(path.clone(), None);
+
+        let commits = git
+            .commits(vec![d[..10].to_string(), a.clone(), "0".repeat(40)])
+            .await;
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[&a].summary, "a");
+        assert_eq!(commits[&a].author, "alice");
+        let ancestry = commits[&d[..10]].ancestry.as_ref().unwrap();
+        assert_eq!(
+            (ancestry.branch.as_str(), ancestry.ahead, ancestry.behind),
+            ("master", 1, 1)
+        );
+
+        let ranges = git
+            .ranges(vec![
+                RangeQuery {
+                    activation_id: 1,
+                    from: a.clone(),
+                    to: c.clone(),
+                },
+                RangeQuery {
+                    activation_id: 2,
+                    from: c.clone(),
+                    to: d.clone(),
+                },
+            ])
+            .await;
+        let summaries = |commits: &[CommitInfo]| {
+            commits
+                .iter()
+                .map(|commit| commit.summary.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summaries(&ranges[0].added), ["c", "b"]);
+        assert!(ranges[0].removed.is_empty());
+        assert_eq!(summaries(&ranges[1].added), ["d"]);
+        assert_eq!(summaries(&ranges[1].removed), ["c"]);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
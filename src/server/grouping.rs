@@ -0,0 +1,279 @@
+//! What the frontpage groups hosts by. Plain keys look up host metadata, keys
+//! starting with `@` are derived from the system the host runs now:
+//! `@branch`, `@commit`, `@user`, `@release` (the NixOS release in the store
+//! path name), `@staleness` (time since the last activation, in buckets),
+//! `@input:<name>` (the revision of a flake input) and `@hostname:<regex>` (the
+//! capture groups of the regex, or the whole match).
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+
+use crate::{server::host_query::QueryError, shared::dto::host::HostStateDto};
+
+const UNGROUPED: &str = "Ungrouped";
+const NEVER_ACTIVATED: &str = "never activated";
+const UNKNOWN_PROVENANCE: &str = "unknown provenance";
+
+/// `nixos-system-<hostname>-25.05pre-git` or `...-24.11.20241201.abcdef0`.
+static RELEASE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"-nixos-system-.*-(\d{2}\.\d{2})").unwrap());
+
+/// Upper bound and name of each staleness bucket.
+const STALENESS_BUCKETS: [(i64, &str); 4] = [
+    (1, "under 1 day"),
+    (7, "1 to 7 days"),
+    (30, "7 to 30 days"),
+    (i64::MAX, "over 30 days"),
+];
+
+#[derive(Debug, Clone)]
+pub(crate) enum GroupingKey {
+    Metadata(String),
+    Branch,
+    Commit,
+    User,
+    Release,
+    Staleness,
+    Input(String),
+    HostnameRegex(Regex),
+}
+
+impl GroupingKey {
+    pub(crate) fn parse(key: &str) -> Result<Self, QueryError> {
+        let Some(derived) = key.strip_prefix('@') else {
+            return Ok(GroupingKey::Metadata(key.to_string()));
+        };
+        if let Some(pattern) = derived.strip_prefix("hostname:") {
+            return Regex::new(pattern)
+                .map(GroupingKey::HostnameRegex)
+                .map_err(|err| QueryError::InvalidRegex(err.to_string()));
+        }
+        if let Some(input) = derived.strip_prefix("input:") {
+            return Ok(GroupingKey::Input(input.to_string()));
+        }
+        match derived {
+            "branch" => Ok(GroupingKey::Branch),
+            "commit" => Ok(GroupingKey::Commit),
+            "user" => Ok(GroupingKey::User),
+            "release" => Ok(GroupingKey::Release),
+            "staleness" => Ok(GroupingKey::Staleness),
+            _ => Err(QueryError::InvalidGroupingKey(key.to_string())),
+        }
+    }
+
+    /// The group of the host, and a rank to order groups by before their name.
+    pub(crate) fn group_of(&self, host: &HostStateDto, now: DateTime<Utc>) -> (usize, String) {
+        let running = host.host.logs.as_ref();
+        let revision = running.and_then(|activation| activation.revision.as_ref());
+        let name = match self {
+            GroupingKey::Metadata(key) => host.host.metadata.get(key).cloned(),
+            GroupingKey::Branch => Some(match (running, revision) {
+                (None, _) => NEVER_ACTIVATED.to_string(),
+                (Some(_), None) => UNKNOWN_PROVENANCE.to_string(),
+                (Some(_), Some(revision)) => revision.branch.clone(),
+            }),
+            GroupingKey::Commit => Some(match (running, revision) {
+                (None, _) => NEVER_ACTIVATED.to_string(),
+                (Some(_), None) => UNKNOWN_PROVENANCE.to_string(),
+                (Some(_), Some(revision)) => revision.commit_hash.clone(),
+            }),
+            GroupingKey::User => Some(
+                running
+                    .map(|activation| activation.username.clone())
+                    .unwrap_or_else(|| NEVER_ACTIVATED.to_string()),
+            ),
+            GroupingKey::Release => Some(match running {
+                None => NEVER_ACTIVATED.to_string(),
+                Some(activation) => nixos_release(&activation.store_path)
+                    .unwrap_or_else(|| "unknown release".to_string()),
+            }),
+            GroupingKey::Staleness => {
+                return match running {
+                    None => (STALENESS_BUCKETS.len(), NEVER_ACTIVATED.to_string()),
+                    Some(activation) => staleness_bucket(now - activation.activated_at),
+                };
+            }
+            GroupingKey::Input(input) => Some(match running {
+                None => NEVER_ACTIVATED.to_string(),
+                Some(_) => host
+                    .running_inputs
+                    .get(input)
+                    .cloned()
+                    .unwrap_or_else(|| format!("unknown {input}")),
+            }),
+            GroupingKey::HostnameRegex(regex) => hostname_capture(regex, &host.host.hostname),
+        };
+        (0, name.unwrap_or_else(|| UNGROUPED.to_string()))
+    }
+}
+
+fn nixos_release(store_path: &str) -> Option<String> {
+    RELEASE
+        .captures(store_path)
+        .map(|captures| captures[1].to_string())
+}
+
+fn staleness_bucket(age: Duration) -> (usize, String) {
+    let days = age.num_days();
+    let rank = STALENESS_BUCKETS
+        .iter()
+        .position(|(below_days, _)| days < *below_days)
+        .unwrap_or(STALENESS_BUCKETS.len() - 1);
+    (rank, STALENESS_BUCKETS[rank].1.to_string())
+}
+
+/// The capture groups joined by spaces, the whole match if there are none.
+fn hostname_capture(regex: &Regex, hostname: &str) -> Option<String> {
+    let captures = regex.captures(hostname)?;
+    if captures.len() == 1 {
+        return Some(captures[0].to_string());
+    }
+    let groups: Vec<&str> = captures
+        .iter()
+        .skip(1)
+        .flatten()
+        .map(|m| m.as_str())
+        .collect();
+    Some(groups.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::model::{
+        activation::{Activation, ActivationCore},
+        host::{HostModel, HostWithState},
+        revision::RevisionModel,
+    };
+    use chrono::TimeZone;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap()
+    }
+
+    fn host(hostname: &str, store_path: &str, days_ago: i64, linked: bool) -> HostStateDto {
+        HostStateDto::from(HostWithState {
+            host: HostModel {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::from([("env".to_string(), "prod".to_string())]),
+            },
+            running: Some(Activation {
+                id: 1,
+                core: ActivationCore {
+                    activated_at: now() - Duration::days(days_ago),
+                    username: "alice".to_string(),
+                    store_path: store_path.to_string(),
+                    activation_type: "switch".to_string(),
+                    hostname: hostname.to_string(),
+                    revision: linked.then(|| RevisionModel {
+                        commit_hash: "abc".to_string(),
+                        branch: "master".to_string(),
+                    }),
+                },
+            }),
+            next_boot: None,
+            latest_test: None,
+            rollbacks: 0,
+            latest_build: None,
+            running_inputs: match linked {
+                true => BTreeMap::from([("nixpkgs".to_string(), "1a2b3c".to_string())]),
+                false => BTreeMap::new(),
+            },
+        })
+    }
+
+    fn group(key: &str, host: &HostStateDto) -> (usize, String) {
+        GroupingKey::parse(key).unwrap().group_of(host, now())
+    }
+
+    #[test]
+    fn test_derived_groups() {
+        let a = host(
+            "web-p01",
+            "/nix/store/4v0ykqdvvpgpw83ljfk32bzjl2bcblmk-nixos-system-web-p01-25.05pre-git",
+            3,
+            true,
+        );
+        let b = host(
+            "db-s02",
+            "/nix/store/z79zirq3imbn761fv32q1pnb2xkpp8ma-nixos-system-db-s02-24.11.20241201.abcdef0",
+            45,
+            false,
+        );
+        assert_eq!(group("env", &a), (0, "prod".to_string()));
+        assert_eq!(group("rack", &a), (0, "Ungrouped".to_string()));
+        assert_eq!(group("@branch", &a), (0, "master".to_string()));
+        assert_eq!(group("@commit", &b), (0, "unknown provenance".to_string()));
+        assert_eq!(group("@user", &a), (0, "alice".to_string()));
+        assert_eq!(group("@release", &a), (0, "25.05".to_string()));
+        assert_eq!(group("@release", &b), (0, "24.11".to_string()));
+        assert_eq!(group("@input:nixpkgs", &a), (0, "1a2b3c".to_string()));
+        assert_eq!(
+            group("@input:nixpkgs", &b),
+            (0, "unknown nixpkgs".to_string())
+        );
+        assert_eq!(group("@staleness", &a), (1, "1 to 7 days".to_string()));
+        assert_eq!(group("@staleness", &b), (3, "over 30 days".to_string()));
+        assert_eq!(
+            group(r"@hostname:^([a-z]+)-([ps])\d+", &a),
+            (0, "web p".to_string())
+        );
+        assert_eq!(group(r"@hostname:-s\d+$", &b), (0, "-s02".to_string()));
+        assert_eq!(group(r"@hostname:^x", &b), (0, "Ungrouped".to_string()));
+    }
+
+    #[test]
+    fn test_input_groups() {
+        let with_nixpkgs = |hostname, rev: Option<&str>| {
+            let mut host = host(hostname, "/nix/store/x", 1, true);
+            host.running_inputs = rev
+                .map(|rev| BTreeMap::from([("nixpkgs".to_string(), rev.to_string())]))
+                .unwrap_or_default();
+            host
+        };
+        let mut never_activated = with_nixpkgs("d", None);
+        never_activated.host.logs = None;
+        let hosts = [
+            with_nixpkgs("a", Some("1a2b3c")),
+            with_nixpkgs("b", Some("4d5e6f")),
+            with_nixpkgs("c", Some("1a2b3c")),
+            with_nixpkgs("e", None),
+            never_activated,
+        ];
+        let groups: Vec<String> = hosts
+            .iter()
+            .map(|host| group("@input:nixpkgs", host).1)
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                "1a2b3c",
+                "4d5e6f",
+                "1a2b3c",
+                "unknown nixpkgs",
+                "never activated"
+            ]
+        );
+        // another input of the same hosts
+        assert_eq!(
+            group("@input:home-manager", &hosts[0]),
+            (0, "unknown home-manager".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(matches!(
+            GroupingKey::parse("@nope"),
+            Err(QueryError::InvalidGroupingKey(_))
+        ));
+        assert!(matches!(
+            GroupingKey::parse("@hostname:(["),
+            Err(QueryError::InvalidRegex(_))
+        ));
+    }
+}
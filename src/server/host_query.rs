@@ -0,0 +1,527 @@
+//! Filter expressions for the host list, like
+//! `env:production branch:!master stale:>7d user:alice commit:abc*`.
+//!
+//! Terms are separated by whitespace and must all match. A term is `field:value`
+//! or a bare word, which matches hostname, metadata values, branch and store path
+//! as a substring. Values are case insensitive globs where `*` matches any run of
+//! characters, `!` in front negates and `,` separates alternatives. Wrap values
+//! containing spaces in double quotes.
+//!
+//! Fields are `host`, `branch`, `commit`, `user`, `type` and `store` of the
+//! system running now, `input.<name>` for the revision of one of its flake
+//! inputs, `stale` compared to a duration like `>7d` or `<=12h`, and metadata
+//! keys, either bare or as `meta.<key>` when a key collides with one of the
+//! fields.
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::shared::model::host::HostWithState;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum QueryError {
+    #[error("unterminated quote in `{0}`")]
+    UnterminatedQuote(String),
+    #[error("`{0}` has no value")]
+    MissingValue(String),
+    #[error("`{0}` is not a duration like >7d, <12h or >=30m")]
+    InvalidDuration(String),
+    #[error("cannot sort by `{0}`")]
+    InvalidSort(String),
+    #[error("cannot group by `{0}`")]
+    InvalidGroupingKey(String),
+    #[error("invalid regex: {0}")]
+    InvalidRegex(String),
+}
+
+/// Something about a host a term or sort key looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HostField {
+    Hostname,
+    Branch,
+    Commit,
+    User,
+    ActivationType,
+    StorePath,
+    Stale,
+    /// Revision of a flake input of the running system.
+    Input(String),
+    Metadata(String),
+}
+
+impl HostField {
+    pub(crate) fn parse(name: &str) -> Self {
+        if let Some(key) = name
+            .strip_prefix("meta.")
+            .or_else(|| name.strip_prefix("metadata."))
+        {
+            return HostField::Metadata(key.to_string());
+        }
+        if let Some(input) = name.strip_prefix("input.") {
+            return HostField::Input(input.to_string());
+        }
+        match name.to_lowercase().as_str() {
+            "host" | "hostname" => HostField::Hostname,
+            "branch" => HostField::Branch,
+            "commit" => HostField::Commit,
+            "user" => HostField::User,
+            "type" => HostField::ActivationType,
+            "store" => HostField::StorePath,
+            "stale" => HostField::Stale,
+            _ => HostField::Metadata(name.to_string()),
+        }
+    }
+
+    /// The value of the field for the host, if it has one.
+    pub(crate) fn value(&self, host: &HostWithState) -> Option<String> {
+        let running = host.running.as_ref().map(|activation| &activation.core);
+        let revision = running.and_then(|core| core.revision.as_ref());
+        match self {
+            HostField::Hostname => Some(host.host.hostname.clone()),
+            HostField::Branch => revision.map(|r| r.branch.clone()),
+            HostField::Commit => revision.map(|r| r.commit_hash.clone()),
+            HostField::User => running.map(|core| core.username.clone()),
+            HostField::ActivationType => running.map(|core| core.activation_type.clone()),
+            HostField::StorePath => running.map(|core| core.store_path.clone()),
+            HostField::Stale => running.map(|core| core.activated_at.to_rfc3339()),
+            HostField::Input(input) => host.running_inputs.get(input).cloned(),
+            HostField::Metadata(key) => host.host.metadata.get(key).cloned(),
+        }
+    }
+}
+
+fn activated_at(host: &HostWithState) -> Option<DateTime<Utc>> {
+    host.running
+        .as_ref()
+        .map(|activation| activation.core.activated_at)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Text(String),
+    Pattern {
+        field: HostField,
+        negated: bool,
+        alternatives: Vec<String>,
+    },
+    Stale {
+        comparison: Comparison,
+        age: Duration,
+    },
+}
+
+impl Term {
+    fn matches(&self, host: &HostWithState, now: DateTime<Utc>) -> bool {
+        match self {
+            Term::Text(text) => {
+                let running = host.running.as_ref().map(|activation| &activation.core);
+                std::iter::once(host.host.hostname.as_str())
+                    .chain(host.host.metadata.values().map(String::as_str))
+                    .chain(running.map(|core| core.store_path.as_str()))
+                    .chain(
+                        running
+                            .and_then(|core| core.revision.as_ref())
+                            .map(|r| r.branch.as_str()),
+                    )
+                    .any(|value| value.to_lowercase().contains(text))
+            }
+            Term::Pattern {
+                field,
+                negated,
+                alternatives,
+            } => {
+                let found = field.value(host).is_some_and(|value| {
+                    let value = value.to_lowercase();
+                    alternatives.iter().any(|glob| glob_match(glob, &value))
+                });
+                found != *negated
+            }
+            Term::Stale { comparison, age } => {
+                // a host that never activated anything is as stale as it gets
+                let Some(activated_at) = activated_at(host) else {
+                    return matches!(comparison, Comparison::Greater | Comparison::GreaterOrEqual);
+                };
+                let host_age = now - activated_at;
+                match comparison {
+                    Comparison::Less => host_age < *age,
+                    Comparison::LessOrEqual => host_age <= *age,
+                    Comparison::Greater => host_age > *age,
+                    Comparison::GreaterOrEqual => host_age >= *age,
+                }
+            }
+        }
+    }
+}
+
+/// A parsed filter expression, empty matches every host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct HostQuery {
+    terms: Vec<Term>,
+}
+
+impl HostQuery {
+    pub(crate) fn parse(expression: &str) -> Result<Self, QueryError> {
+        let terms = tokenize(expression)?
+            .into_iter()
+            .map(|token| parse_term(&token))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    pub(crate) fn matches(&self, host: &HostWithState, now: DateTime<Utc>) -> bool {
+        self.terms.iter().all(|term| term.matches(host, now))
+    }
+}
+
+/// Parses the `q` and `sort` query parameters, missing ones match everything
+/// and sort by hostname.
+pub(crate) fn parse_host_query(
+    q: Option<&str>,
+    sort: Option<&str>,
+) -> Result<(HostQuery, HostSort), QueryError> {
+    Ok((
+        HostQuery::parse(q.unwrap_or_default())?,
+        HostSort::parse(sort.unwrap_or_default())?,
+    ))
+}
+
+/// Comma separated fields to sort hosts by, `-` in front sorts descending.
+/// Hosts without a value come last. `stale` sorts the least recently activated first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct HostSort {
+    keys: Vec<(HostField, bool)>,
+}
+
+impl HostSort {
+    pub(crate) fn parse(sort: &str) -> Result<Self, QueryError> {
+        let keys = sort
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (descending, name) = match key.strip_prefix('-') {
+                    Some(name) => (true, name),
+                    None => (false, key),
+                };
+                if name.is_empty() || name.contains(':') {
+                    return Err(QueryError::InvalidSort(key.to_string()));
+                }
+                Ok((HostField::parse(name), descending))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { keys })
+    }
+
+    pub(crate) fn sort(&self, hosts: &mut [HostWithState]) {
+        hosts.sort_by(|a, b| {
+            self.keys
+                .iter()
+                .map(|(field, descending)| compare(field, *descending, a, b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.host.hostname.cmp(&b.host.hostname))
+        });
+    }
+}
+
+fn compare(field: &HostField, descending: bool, a: &HostWithState, b: &HostWithState) -> Ordering {
+    match field {
+        // oldest activation, the stalest host, first
+        HostField::Stale => none_last(activated_at(a), activated_at(b), descending),
+        _ => none_last(field.value(a), field.value(b), descending),
+    }
+}
+
+/// Orders the values, `descending` reverses only them and keeps hosts without
+/// a value last.
+fn none_last<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in expression.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        return Err(QueryError::UnterminatedQuote(expression.to_string()));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(token: &str) -> Result<Term, QueryError> {
+    let Some((name, value)) = token.split_once(':') else {
+        return Ok(Term::Text(token.to_lowercase()));
+    };
+    if value.is_empty() {
+        return Err(QueryError::MissingValue(token.to_string()));
+    }
+    let field = HostField::parse(name);
+    if field == HostField::Stale {
+        return parse_stale(value).ok_or_else(|| QueryError::InvalidDuration(value.to_string()));
+    }
+    let (negated, value) = match value.strip_prefix('!') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    Ok(Term::Pattern {
+        field,
+        negated,
+        alternatives: value
+            .split(',')
+            .filter(|alternative| !alternative.is_empty())
+            .map(str::to_lowercase)
+            .collect(),
+    })
+}
+
+fn parse_stale(value: &str) -> Option<Term> {
+    let (comparison, rest) = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+    ]
+    .into_iter()
+    .find_map(|(op, comparison)| value.strip_prefix(op).map(|rest| (comparison, rest)))
+    .unwrap_or((Comparison::Greater, value));
+    Some(Term::Stale {
+        comparison,
+        age: parse_duration(rest)?,
+    })
+}
+
+/// A number followed by `s`, `m`, `h`, `d` or `w`.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let unit_at = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(unit_at);
+    let amount: i64 = amount.parse().ok()?;
+    match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// Matches `text` against `glob` where `*` is any run of characters.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::model::{
+        activation::{Activation, ActivationCore},
+        host::HostModel,
+        revision::RevisionModel,
+    };
+    use chrono::TimeZone;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap()
+    }
+
+    fn host(hostname: &str, env: &str, running: Option<(&str, &str, i64)>) -> HostWithState {
+        HostWithState {
+            host: HostModel {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::from([("env".to_string(), env.to_string())]),
+            },
+            running: running.map(|(branch, user, days_ago)| Activation {
+                id: 1,
+                core: ActivationCore {
+                    activated_at: now() - Duration::days(days_ago),
+                    username: user.to_string(),
+                    store_path: format!("/nix/store/{hostname}"),
+                    activation_type: "switch".to_string(),
+                    hostname: hostname.to_string(),
+                    revision: Some(RevisionModel {
+                        commit_hash: format!("abc{days_ago}"),
+                        branch: branch.to_string(),
+                    }),
+                },
+            }),
+            next_boot: None,
+            latest_test: None,
+            rollbacks: 0,
+            latest_build: None,
+            running_inputs: running
+                .map(|(branch, _, _)| {
+                    BTreeMap::from([("nixpkgs".to_string(), format!("{branch}-rev"))])
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn matching(expression: &str, hosts: &[HostWithState]) -> Vec<String> {
+        let query = HostQuery::parse(expression).unwrap();
+        hosts
+            .iter()
+            .filter(|h| query.matches(h, now()))
+            .map(|h| h.host.hostname.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_query_terms() {
+        let hosts = vec![
+            host("a", "production", Some(("master", "alice", 1))),
+            host("b", "production", Some(("feature", "bob", 10))),
+            host("c", "staging", Some(("master", "alice", 30))),
+            host("d", "staging", None),
+        ];
+        assert_eq!(matching("", &hosts), ["a", "b", "c", "d"]);
+        assert_eq!(matching("env:production", &hosts), ["a", "b"]);
+        assert_eq!(matching("branch:!master", &hosts), ["b", "d"]);
+        assert_eq!(matching("stale:>7d", &hosts), ["b", "c", "d"]);
+        assert_eq!(matching("stale:<=1d", &hosts), ["a"]);
+        assert_eq!(matching("user:ALICE commit:abc3*", &hosts), ["c"]);
+        assert_eq!(
+            matching("env:prod*,staging host:!c", &hosts),
+            ["a", "b", "d"]
+        );
+        assert_eq!(matching("meta.env:staging", &hosts), ["c", "d"]);
+        assert_eq!(matching("featu", &hosts), ["b"]);
+        assert_eq!(matching("input.nixpkgs:feature-*", &hosts), ["b"]);
+        assert_eq!(matching("input.nixpkgs:!master-rev", &hosts), ["b", "d"]);
+    }
+
+    #[test]
+    fn test_input_terms() {
+        let with_inputs = |hostname, inputs: &[(&str, &str)]| {
+            let mut host = host(hostname, "production", Some(("master", "alice", 1)));
+            host.running_inputs = inputs
+                .iter()
+                .map(|(name, rev)| (name.to_string(), rev.to_string()))
+                .collect();
+            host
+        };
+        let hosts = vec![
+            with_inputs("a", &[("nixpkgs", "4d5e6f"), ("home-manager", "9f8e7d")]),
+            with_inputs("b", &[("nixpkgs", "1a2b3c")]),
+            // running a system CI reported no inputs for
+            with_inputs("c", &[]),
+            host("d", "staging", None),
+        ];
+        assert_eq!(matching("input.nixpkgs:1a2b3c", &hosts), ["b"]);
+        assert_eq!(matching("input.nixpkgs:1A2B*,4d5e6f", &hosts), ["a", "b"]);
+        assert_eq!(matching("input.nixpkgs:*", &hosts), ["a", "b"]);
+        assert_eq!(matching("input.nixpkgs:!*", &hosts), ["c", "d"]);
+        assert_eq!(
+            matching("input.home-manager:!9f8e7d", &hosts),
+            ["b", "c", "d"]
+        );
+        assert_eq!(matching("input.disko:*", &hosts), Vec::<String>::new());
+        // revisions are not part of the bare word search
+        assert_eq!(matching("4d5e6f", &hosts), Vec::<String>::new());
+
+        let mut sorted = hosts.clone();
+        HostSort::parse("input.nixpkgs").unwrap().sort(&mut sorted);
+        let hostnames: Vec<&str> = sorted.iter().map(|h| h.host.hostname.as_str()).collect();
+        // hosts without the input come last
+        assert_eq!(hostnames, ["b", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert_eq!(
+            HostQuery::parse("stale:>7y"),
+            Err(QueryError::InvalidDuration(">7y".to_string()))
+        );
+        assert_eq!(
+            HostQuery::parse("env:"),
+            Err(QueryError::MissingValue("env:".to_string()))
+        );
+        assert!(matches!(
+            HostQuery::parse(r#"env:"a b"#),
+            Err(QueryError::UnterminatedQuote(_))
+        ));
+        assert!(HostQuery::parse(r#"env:"a b""#).is_ok());
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut hosts = vec![
+            host("a", "production", Some(("master", "alice", 1))),
+            host("b", "staging", None),
+            host("c", "production", Some(("feature", "bob", 10))),
+        ];
+        HostSort::parse("stale").unwrap().sort(&mut hosts);
+        let order: Vec<&str> = hosts.iter().map(|h| h.host.hostname.as_str()).collect();
+        assert_eq!(order, ["c", "a", "b"]);
+
+        HostSort::parse("-env,branch").unwrap().sort(&mut hosts);
+        let order: Vec<&str> = hosts.iter().map(|h| h.host.hostname.as_str()).collect();
+        assert_eq!(order, ["b", "c", "a"]);
+
+        // b never activated, so it has neither branch nor activation time
+        HostSort::parse("-branch").unwrap().sort(&mut hosts);
+        let order: Vec<&str> = hosts.iter().map(|h| h.host.hostname.as_str()).collect();
+        assert_eq!(order, ["a", "c", "b"]);
+
+        HostSort::parse("-stale").unwrap().sort(&mut hosts);
+        let order: Vec<&str> = hosts.iter().map(|h| h.host.hostname.as_str()).collect();
+        assert_eq!(order, ["a", "c", "b"]);
+
+        assert!(HostSort::parse("env:x").is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("abc*", "abcdef"));
+        assert!(glob_match("*def", "abcdef"));
+        assert!(glob_match("a*c*f", "abcdef"));
+        assert!(glob_match("abc", "abc"));
+        assert!(!glob_match("abc", "abcd"));
+        assert!(!glob_match("a*a", "a"));
+    }
+}
@@ -0,0 +1,354 @@
+use std::{collections::HashMap, sync::atomic::AtomicU64};
+
+use chrono::{DateTime, Utc};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    registry::{Registry, Unit},
+};
+
+use crate::shared::model::{
+    desired_revision::{ComplianceStatus, HostCompliance},
+    host::HostMetricsRow,
+};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct HostLabels {
+    host: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CommitLabels {
+    commit: String,
+    branch: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct BranchLabels {
+    branch: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct GroupLabels {
+    grouping_key: String,
+    group: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ComplianceLabels {
+    grouping_key: String,
+    group: String,
+    status: String,
+}
+
+/// Used as commit and branch for hosts whose store path has no known revision.
+const UNKNOWN: &str = "unknown";
+
+#[derive(Debug)]
+pub(crate) struct ServerMetrics {
+    registry: Registry,
+    hosts: Gauge,
+    hosts_retired: Gauge,
+    last_activation_age: Family<HostLabels, Gauge<f64, AtomicU64>>,
+    hosts_by_commit: Family<CommitLabels, Gauge>,
+    hosts_by_branch: Family<BranchLabels, Gauge>,
+    hosts_by_compliance: Family<ComplianceLabels, Gauge>,
+    hosts_overdue: Family<GroupLabels, Gauge>,
+    activations_received: Counter,
+    activations_inserted: Counter,
+    links_received: Counter,
+    links_inserted: Counter,
+    unknown_provenance: Family<HostLabels, Counter>,
+}
+
+impl ServerMetrics {
+    pub(crate) fn new() -> Self {
+        let mut registry = Registry::with_prefix("hostmap");
+        let hosts = Gauge::default();
+        registry.register("hosts", "Known hosts, retired included", hosts.clone());
+        let hosts_retired = Gauge::default();
+        registry.register(
+            "hosts_retired",
+            "Hosts marked as retired, they are no longer expected to activate",
+            hosts_retired.clone(),
+        );
+        let last_activation_age = Family::default();
+        registry.register_with_unit(
+            "host_last_activation_age",
+            "Time since the latest activation of a host that is not retired",
+            Unit::Seconds,
+            last_activation_age.clone(),
+        );
+        let hosts_by_commit = Family::default();
+        registry.register(
+            "hosts_by_commit",
+            "Hosts that are not retired by the commit of their current store path",
+            hosts_by_commit.clone(),
+        );
+        let hosts_by_branch = Family::default();
+        registry.register(
+            "hosts_by_branch",
+            "Hosts that are not retired by the branch of their current store path",
+            hosts_by_branch.clone(),
+        );
+        let hosts_by_compliance = Family::default();
+        registry.register(
+            "hosts_by_compliance",
+            "Hosts in a group with a desired revision by whether they run it",
+            hosts_by_compliance.clone(),
+        );
+        let hosts_overdue = Family::default();
+        registry.register(
+            "hosts_overdue",
+            "Hosts not running the desired revision of their group past its deadline",
+            hosts_overdue.clone(),
+        );
+        let activations_received = Counter::default();
+        registry.register(
+            "activations_received",
+            "Activations sent to the server",
+            activations_received.clone(),
+        );
+        let activations_inserted = Counter::default();
+        registry.register(
+            "activations_inserted",
+            "Activations the server had not seen before",
+            activations_inserted.clone(),
+        );
+        let links_received = Counter::default();
+        registry.register(
+            "links_received",
+            "Store path to commit links sent to the server",
+            links_received.clone(),
+        );
+        let links_inserted = Counter::default();
+        registry.register(
+            "links_inserted",
+            "Store path to commit links the server had not seen before",
+            links_inserted.clone(),
+        );
+        let unknown_provenance = Family::default();
+        registry.register(
+            "unknown_provenance_activations",
+            "Activations received of store paths CI has not linked to a commit",
+            unknown_provenance.clone(),
+        );
+        Self {
+            registry,
+            hosts,
+            hosts_retired,
+            last_activation_age,
+            hosts_by_commit,
+            hosts_by_branch,
+            hosts_by_compliance,
+            hosts_overdue,
+            activations_received,
+            activations_inserted,
+            links_received,
+            links_inserted,
+            unknown_provenance,
+        }
+    }
+
+    pub(crate) fn observe_activations(&self, received: usize, inserted: u64) {
+        self.activations_received.inc_by(received as u64);
+        self.activations_inserted.inc_by(inserted);
+    }
+
+    pub(crate) fn observe_links(&self, received: usize, inserted: u64) {
+        self.links_received.inc_by(received as u64);
+        self.links_inserted.inc_by(inserted);
+    }
+
+    pub(crate) fn observe_unknown_provenance(&self, host: &str, activations: usize) {
+        self.unknown_provenance
+            .get_or_create(&HostLabels {
+                host: host.to_string(),
+            })
+            .inc_by(activations as u64);
+    }
+
+    /// Replaces the fleet gauges with what is in the database now.
+    fn set_fleet(
+        &self,
+        rows: &[HostMetricsRow],
+        compliance: &[HostCompliance],
+        now: DateTime<Utc>,
+    ) {
+        self.last_activation_age.clear();
+        self.hosts_by_commit.clear();
+        self.hosts_by_branch.clear();
+        self.set_compliance(compliance);
+
+        let mut by_commit: HashMap<CommitLabels, i64> = HashMap::new();
+        let mut by_branch: HashMap<BranchLabels, i64> = HashMap::new();
+        for row in rows.iter().filter(|row| !row.retired) {
+            let Some(activated_at) = row.activated_at else {
+                continue;
+            };
+            let age = (now - activated_at).num_milliseconds() as f64 / 1000.0;
+            self.last_activation_age
+                .get_or_create(&HostLabels {
+                    host: row.hostname.clone(),
+                })
+                .set(age);
+
+            let branch = row.branch.as_deref().unwrap_or(UNKNOWN).to_string();
+            let commit = row.commit_hash.as_deref().unwrap_or(UNKNOWN).to_string();
+            *by_commit
+                .entry(CommitLabels {
+                    commit,
+                    branch: branch.clone(),
+                })
+                .or_default() += 1;
+            *by_branch.entry(BranchLabels { branch }).or_default() += 1;
+        }
+        for (labels, count) in by_commit {
+            self.hosts_by_commit.get_or_create(&labels).set(count);
+        }
+        for (labels, count) in by_branch {
+            self.hosts_by_branch.get_or_create(&labels).set(count);
+        }
+        self.hosts.set(rows.len() as i64);
+        self.hosts_retired
+            .set(rows.iter().filter(|row| row.retired).count() as i64);
+    }
+
+    /// Every status of a group with hosts is set, zero included, so it can be alerted on.
+    fn set_compliance(&self, compliance: &[HostCompliance]) {
+        self.hosts_by_compliance.clear();
+        self.hosts_overdue.clear();
+
+        let mut by_group: HashMap<GroupLabels, HashMap<ComplianceStatus, i64>> = HashMap::new();
+        let mut overdue: HashMap<GroupLabels, i64> = HashMap::new();
+        for host in compliance {
+            let group = GroupLabels {
+                grouping_key: host.desired.grouping_key.clone(),
+                group: host.desired.group_value.clone(),
+            };
+            *overdue.entry(group.clone()).or_default() += i64::from(host.overdue);
+            *by_group
+                .entry(group)
+                .or_default()
+                .entry(host.status)
+                .or_default() += 1;
+        }
+        for (group, statuses) in by_group {
+            for status in [
+                ComplianceStatus::OnTarget,
+                ComplianceStatus::Behind,
+                ComplianceStatus::Unknown,
+            ] {
+                self.hosts_by_compliance
+                    .get_or_create(&ComplianceLabels {
+                        grouping_key: group.grouping_key.clone(),
+                        group: group.group.clone(),
+                        status: status.as_str().to_string(),
+                    })
+                    .set(statuses.get(&status).copied().unwrap_or(0));
+            }
+        }
+        for (group, count) in overdue {
+            self.hosts_overdue.get_or_create(&group).set(count);
+        }
+    }
+
+    pub(crate) fn encode(
+        &self,
+        rows: &[HostMetricsRow],
+        compliance: &[HostCompliance],
+    ) -> Result<String, std::fmt::Error> {
+        self.set_fleet(rows, compliance, Utc::now());
+        let mut body = String::new();
+        encode(&mut body, &self.registry)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row(
+        hostname: &str,
+        retired: bool,
+        commit: Option<&str>,
+        minutes_ago: i64,
+    ) -> HostMetricsRow {
+        let now = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        HostMetricsRow {
+            hostname: hostname.to_string(),
+            retired,
+            activated_at: Some(now - chrono::Duration::minutes(minutes_ago)),
+            commit_hash: commit.map(str::to_string),
+            branch: commit.map(|_| "master".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_fleet_gauges() {
+        let metrics = ServerMetrics::new();
+        let rows = vec![
+            row("a", false, Some("abc"), 1),
+            row("b", false, Some("abc"), 2),
+            row("c", false, None, 3),
+            row("d", true, Some("def"), 4),
+        ];
+        let now = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        metrics.set_fleet(&rows, &[], now);
+        metrics.observe_activations(10, 4);
+        let mut body = String::new();
+        encode(&mut body, &metrics.registry).unwrap();
+
+        assert!(body.contains("hostmap_hosts 4"));
+        assert!(body.contains("hostmap_hosts_retired 1"));
+        assert!(body.contains(r#"hostmap_host_last_activation_age_seconds{host="b"} 120.0"#));
+        assert!(!body.contains(r#"host="d""#));
+        assert!(body.contains(r#"hostmap_hosts_by_commit{commit="abc",branch="master"} 2"#));
+        assert!(body.contains(r#"hostmap_hosts_by_commit{commit="unknown",branch="unknown"} 1"#));
+        assert!(body.contains(r#"hostmap_hosts_by_branch{branch="master"} 2"#));
+        assert!(body.contains("hostmap_activations_received_total 10"));
+        assert!(body.contains("hostmap_activations_inserted_total 4"));
+    }
+
+    #[test]
+    fn test_compliance_gauges() {
+        use crate::shared::model::desired_revision::{DesiredRevisionModel, DesiredTarget};
+
+        let now = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        let host = |hostname: &str, status: ComplianceStatus, overdue: bool| HostCompliance {
+            hostname: hostname.to_string(),
+            desired: DesiredRevisionModel {
+                grouping_key: "env".to_string(),
+                group_value: "prod".to_string(),
+                target: DesiredTarget::Commit("abc".to_string()),
+                deadline: None,
+                author: "alice".to_string(),
+                set_at: now,
+            },
+            status,
+            overdue,
+        };
+        let metrics = ServerMetrics::new();
+        metrics.set_fleet(
+            &[],
+            &[
+                host("a", ComplianceStatus::OnTarget, false),
+                host("b", ComplianceStatus::Behind, true),
+                host("c", ComplianceStatus::Behind, false),
+            ],
+            now,
+        );
+        let mut body = String::new();
+        encode(&mut body, &metrics.registry).unwrap();
+
+        assert!(body.contains(
+            r#"hostmap_hosts_by_compliance{grouping_key="env",group="prod",status="behind"} 2"#
+        ));
+        assert!(body.contains(
+            r#"hostmap_hosts_by_compliance{grouping_key="env",group="prod",status="unknown"} 0"#
+        ));
+        assert!(body.contains(r#"hostmap_hosts_overdue{grouping_key="env",group="prod"} 1"#));
+    }
+}
@@ -1,9 +1,18 @@
 mod api_authentication;
+mod branch_precedence;
 mod controller;
 mod custom_error;
 pub(crate) mod endpoint;
+mod events;
+mod export;
+mod forge;
+mod git;
+mod grouping;
+mod host_query;
+mod metrics;
 mod repository;
 mod service;
+mod webhook;
 
 use crate::cli::ServerArgs;
 use crate::server::api_authentication::api_authentication;
@@ -14,37 +23,67 @@ use axum::http::header;
 use axum::middleware::from_fn_with_state;
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{get, post, put},
 };
 use sqlx::{Pool, Postgres, postgres::PgPoolOptions};
 use tera::{Tera, Value, try_get_value};
 use tower_http::sensitive_headers::SetSensitiveRequestHeadersLayer;
 
 use crate::server::{
+    branch_precedence::BranchPrecedence,
     controller::{activation_controller, host_controller},
     custom_error::RetError,
+    events::HostEvents,
+    forge::ForgeLinks,
+    git::GitRepository,
+    metrics::ServerMetrics,
     service::{
-        activation_service::ActivationLogService, host_service::HostService,
-        nix_git_link_service::NixGitLinkService,
+        activation_service::ActivationLogService, annotation_service::AnnotationService,
+        desired_revision_service::DesiredRevisionService, host_service::HostService,
+        nix_git_link_service::NixGitLinkService, package_service::PackageService,
+        rollout_service::RolloutService, store_path_service::StorePathService,
     },
+    webhook::UnknownProvenanceWebhook,
 };
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
 
 pub const TIME_ZONE_ENV_NAME: &str = "TIME_ZONE_HOSTMAP";
 
+/// The time zone in `TIME_ZONE_HOSTMAP`, UTC if unset or unknown.
+pub(crate) fn configured_time_zone() -> Tz {
+    std::env::var(TIME_ZONE_ENV_NAME)
+        .ok()
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(chrono_tz::UTC)
+}
+
 #[derive(Debug, Clone)]
 struct ServerConfig {
-    default_grouping_key: Option<String>,
+    default_grouping_keys: Vec<String>,
     columns: Vec<String>,
-    repo_url: String,
+    /// Flake inputs shown as columns after the metadata ones.
+    input_columns: Vec<String>,
 }
 impl ServerConfig {
-    fn new(default_grouping_key: Option<String>, columns: Vec<String>, repo_url: String) -> Self {
+    /// The outermost default grouping key, for pages that group by a single key.
+    fn default_grouping_key(&self) -> Option<&String> {
+        self.default_grouping_keys.first()
+    }
+
+    fn new(
+        default_grouping_keys: Vec<String>,
+        columns: Vec<String>,
+        input_columns: Vec<String>,
+    ) -> Self {
         Self {
-            default_grouping_key,
+            default_grouping_keys,
             columns,
-            repo_url,
+            input_columns: input_columns
+                .into_iter()
+                .map(|input| input.trim().to_string())
+                .filter(|input| !input.is_empty())
+                .collect(),
         }
     }
 }
@@ -55,24 +94,15 @@ struct ServerState {
     host_service: HostService,
     activation_log_service: ActivationLogService,
     nix_git_link_service: NixGitLinkService,
-}
-
-impl ServerState {
-    fn new(
-        tera: Arc<Tera>,
-        server_config: ServerConfig,
-        host_service: HostService,
-        activation_log_service: ActivationLogService,
-        nix_git_link_service: NixGitLinkService,
-    ) -> Self {
-        Self {
-            tera,
-            server_config,
-            host_service,
-            activation_log_service,
-            nix_git_link_service,
-        }
-    }
+    store_path_service: StorePathService,
+    rollout_service: RolloutService,
+    annotation_service: AnnotationService,
+    desired_revision_service: DesiredRevisionService,
+    host_events: HostEvents,
+    metrics: Arc<ServerMetrics>,
+    unknown_provenance_webhook: Option<UnknownProvenanceWebhook>,
+    git_repository: Option<GitRepository>,
+    package_service: PackageService,
 }
 
 async fn build_pool(database_url: String) -> Result<Pool<Postgres>, sqlx::Error> {
@@ -90,16 +120,110 @@ fn create_public_router() -> Router<ServerState> {
             endpoint::frontpage(),
             get(controller::frontpage::render_frontpage),
         )
+        .route(endpoint::hosts(), get(host_controller::get_hosts))
+        .route(
+            endpoint::host_activations(),
+            get(activation_controller::get_activations),
+        )
+        .route(
+            endpoint::activity(),
+            get(controller::activity::render_activity_page),
+        )
+        .route(
+            endpoint::activity_api(),
+            get(controller::activity::get_activity),
+        )
+        .route(
+            endpoint::frontpage_export(),
+            get(controller::export::export_frontpage),
+        )
+        .route(
+            endpoint::history_export(),
+            get(controller::export::export_history),
+        )
+        .route(
+            endpoint::fleet_feed(),
+            get(controller::feed::get_fleet_feed),
+        )
+        .route(endpoint::host_feed(), get(controller::feed::get_host_feed))
+        .route(
+            endpoint::group_feed(),
+            get(controller::feed::get_group_feed),
+        )
+        .route(
+            endpoint::host_events(),
+            get(controller::events::stream_host_events),
+        )
+        .route(
+            endpoint::host_row(),
+            get(controller::frontpage::render_host_row),
+        )
+        .route(
+            endpoint::rollout(),
+            get(controller::rollout::render_store_paths_rollout_page),
+        )
+        .route(
+            endpoint::commit_rollout(),
+            get(controller::rollout::render_commit_rollout_page),
+        )
+        .route(
+            endpoint::rollout_api(),
+            get(controller::rollout::get_store_paths_rollout),
+        )
+        .route(
+            endpoint::commit_rollout_api(),
+            get(controller::rollout::get_commit_rollout),
+        )
+        .route(
+            endpoint::store_path(),
+            get(controller::store_path::render_store_path_page),
+        )
+        .route(
+            endpoint::store_path_api(),
+            get(controller::store_path::get_store_path),
+        )
+        .route(
+            endpoint::package_diff(),
+            get(controller::package::render_package_diff_page),
+        )
+        .route(
+            endpoint::package_diff_api(),
+            get(controller::package::get_package_diff),
+        )
+        .route(
+            endpoint::unknown_provenance_report(),
+            get(controller::report::render_unknown_provenance_report),
+        )
+        .route(
+            endpoint::metrics(),
+            get(controller::metrics_controller::render_metrics),
+        )
         .route(
             endpoint::history(),
             get(controller::history::render_history_page),
         )
+        .route(
+            endpoint::host_annotations(),
+            get(controller::annotation::get_annotations),
+        )
+        .route(
+            endpoint::desired_revisions(),
+            get(controller::desired_revision::get_desired_revisions),
+        )
+        .route(
+            endpoint::compliance(),
+            get(controller::desired_revision::get_compliance),
+        )
 }
 fn create_protected_router(api_key: String) -> Router<ServerState> {
     let sensitive_headers: Vec<header::HeaderName> = vec![header::AUTHORIZATION];
 
     Router::new()
         .route(endpoint::hosts_bulk(), post(host_controller::create_hosts))
+        .route(
+            endpoint::retire_host(),
+            post(host_controller::retire_host).delete(host_controller::unretire_host),
+        )
         .route(
             endpoint::activations_bulk(),
             post(activation_controller::create_activation),
@@ -112,6 +236,27 @@ fn create_protected_router(api_key: String) -> Router<ServerState> {
             endpoint::nix_git_link_bulk(),
             post(controller::nix_git_link_controller::create_links),
         )
+        .route(
+            endpoint::packages(),
+            post(controller::package::upload_packages),
+        )
+        .route(
+            endpoint::host_annotations(),
+            post(controller::annotation::create_annotation),
+        )
+        .route(
+            endpoint::annotation(),
+            put(controller::annotation::update_annotation),
+        )
+        .route(
+            endpoint::expire_annotation(),
+            post(controller::annotation::expire_annotation),
+        )
+        .route(
+            endpoint::group_desired_revision(),
+            put(controller::desired_revision::set_desired_revision)
+                .delete(controller::desired_revision::clear_desired_revision),
+        )
         .layer(SetSensitiveRequestHeadersLayer::new(sensitive_headers))
         .layer(from_fn_with_state(api_key, api_authentication))
 }
@@ -120,13 +265,27 @@ pub async fn run(
     ServerArgs {
         database_url,
         api_key_file,
-        default_grouping_key,
+        default_grouping_keys,
         url,
         port,
         columns,
+        input_columns,
         repo_url,
+        commit_url_template,
+        compare_url_template,
+        branch_url_template,
+        unknown_provenance_webhook,
+        branch_precedence,
+        git_repo_path,
+        git_default_branch,
     }: ServerArgs,
 ) -> Result<(), Box<dyn error::Error + Send + Sync + 'static>> {
+    let forge_links = ForgeLinks::new(
+        commit_url_template,
+        compare_url_template,
+        branch_url_template,
+        repo_url,
+    )?;
     let api_key = read_api_key(&api_key_file);
     let pool = build_pool(database_url).await?;
     sqlx::migrate!()
@@ -137,24 +296,43 @@ pub async fn run(
 
     let templates_dir = std::env::var("HOSTMAP_TEMPLATES_DIR").expect("user must specificy the env variable HOSTMAP_TEMPLATES_DIR. This should not be something end user needs to specify, since the flake will wrap this.");
     tracing::info!("Using templates directory: {}", &templates_dir);
-    let host_service = HostService::new(pool.clone());
-    let log_service = ActivationLogService::new(pool.clone());
+    let branch_precedence = BranchPrecedence::new(&branch_precedence);
+    tracing::info!(patterns = ?branch_precedence.like_patterns(), "resolving revisions by branch precedence");
+    let host_service = HostService::new(pool.clone(), branch_precedence.clone());
+    let package_service = PackageService::new(pool.clone(), branch_precedence.clone());
+    let log_service = ActivationLogService::new(pool.clone(), branch_precedence);
     let nix_git_link_service = NixGitLinkService::new(pool.clone());
-    let tera = Arc::new(load_tera(&templates_dir).unwrap_or_else(|_| {
+    let store_path_service = StorePathService::new(pool.clone());
+    let rollout_service = RolloutService::new(pool.clone(), store_path_service.clone());
+    let tera = Arc::new(load_tera(&templates_dir, &forge_links).unwrap_or_else(|_| {
         panic!(
             "Failed to load templates from directory: {}",
             &templates_dir
         )
     }));
-    let server_config =
-        ServerConfig::new(default_grouping_key, columns.unwrap_or_default(), repo_url);
-    let server_state = ServerState::new(
+    let host_events = HostEvents::new();
+    host_events.spawn_listener(pool.clone(), host_service.clone());
+    let server_config = ServerConfig::new(
+        default_grouping_keys,
+        columns.unwrap_or_default(),
+        input_columns,
+    );
+    let server_state = ServerState {
         tera,
         server_config,
         host_service,
-        log_service,
+        activation_log_service: log_service,
         nix_git_link_service,
-    );
+        store_path_service,
+        rollout_service,
+        annotation_service: AnnotationService::new(pool.clone()),
+        desired_revision_service: DesiredRevisionService::new(pool.clone()),
+        host_events,
+        metrics: Arc::new(ServerMetrics::new()),
+        unknown_provenance_webhook: unknown_provenance_webhook.map(UnknownProvenanceWebhook::new),
+        git_repository: git_repo_path.map(|path| GitRepository::new(path, git_default_branch)),
+        package_service,
+    };
     let router = create_public_router()
         .merge(create_protected_router(api_key))
         .fallback(custom_error::fallback)
@@ -184,12 +362,15 @@ pub async fn run(
         .unwrap();
     Ok(())
 }
-fn load_tera(templates_dir: &str) -> Result<Tera, tera::Error> {
+fn load_tera(templates_dir: &str, forge_links: &ForgeLinks) -> Result<Tera, tera::Error> {
     let tera_pattern = format!("{}/**/*", templates_dir);
     let mut tera = Tera::new(&tera_pattern)?;
     tera.register_filter("nix_name", nix_name);
+    tera.register_filter("store_path_key", store_path_key);
     tera.register_filter("format_utc_as_local", format_utc_as_local);
     tera.register_filter("format_utc_as_local_time", format_utc_as_local_time);
+    tera.register_filter("format_duration", format_duration);
+    forge_links.register(&mut tera);
     Ok(tera)
 }
 
@@ -197,6 +378,13 @@ fn nix_name(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value
     let s = try_get_value!("nix_name", "value", String, value);
     Ok(Value::String(nix_name_fn(&s).unwrap_or(s)))
 }
+/// The part of a store path used in `/store-path/{key}` links.
+fn store_path_key(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let s = try_get_value!("store_path_key", "value", String, value);
+    Ok(Value::String(
+        s.strip_prefix("/nix/store/").unwrap_or(&s).to_string(),
+    ))
+}
 fn nix_name_fn(s: &str) -> Option<String> {
     let s = s.strip_prefix("/nix/store/")?.strip_suffix("pre-git")?;
     let (prefix, rest) = s.split_at(10);
@@ -222,6 +410,26 @@ pub fn format_utc_as_local_time(
     ))
 }
 
+/// Seconds as e.g. `1d 2h 3m 4s`, leaving out leading zero units.
+fn format_duration_fn(seconds: i64) -> String {
+    let units = [(86400, "d"), (3600, "h"), (60, "m")];
+    let mut rest = seconds.max(0);
+    let mut out = String::new();
+    for (size, unit) in units {
+        if rest >= size || !out.is_empty() {
+            out.push_str(&format!("{}{} ", rest / size, unit));
+            rest %= size;
+        }
+    }
+    out.push_str(&format!("{rest}s"));
+    out
+}
+
+fn format_duration(value: &Value, _args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let seconds = try_get_value!("format_duration", "value", i64, value);
+    Ok(Value::String(format_duration_fn(seconds)))
+}
+
 pub fn format_utc_as_local_fn(rfc3339: &str, tz: &str) -> Option<String> {
     let tz: Tz = tz.parse().ok()?;
     let dt_fixed = DateTime::parse_from_rfc3339(rfc3339).ok()?;
@@ -260,6 +468,12 @@ mod tests {
         assert_eq!(shortened_store_path.unwrap(), correct);
     }
     #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration_fn(42), "42s");
+        assert_eq!(format_duration_fn(3600), "1h 0m 0s");
+        assert_eq!(format_duration_fn(90061), "1d 1h 1m 1s");
+    }
+    #[test]
     fn test_copenhagen_winter_timezone() {
         // Jan 5 is winter time (CET, UTC+1)
         let s = "2026-01-05T12:34:56Z";
@@ -1,7 +1,16 @@
-use crate::shared::model::activation::{ActivationWithRevision, NewActivation};
+use std::collections::HashMap;
+
+use crate::shared::model::{
+    activation::{
+        Activation, ActivationCore, ActivationWithRevision, DEPLOY_ACTIVATION_TYPES, HistoryEntry,
+        NewActivation,
+    },
+    activity::{ActivityFilter, ActivityRow},
+};
+use chrono::{DateTime, Utc};
 use sqlx::{Pool, Postgres, QueryBuilder};
 
-use crate::server::custom_error::RetError;
+use crate::server::{branch_precedence::BranchPrecedence, custom_error::RetError};
 
 #[derive(Debug, Clone)]
 pub struct ActivationRepository;
@@ -10,9 +19,9 @@ impl ActivationRepository {
     pub(crate) async fn insert_many(
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
         log_models: &[NewActivation],
-    ) -> Result<u64, RetError> {
+    ) -> Result<Vec<i64>, RetError> {
         const CHUNK_SIZE: usize = 1000;
-        let mut i = 0;
+        let mut inserted_ids = Vec::new();
         for chunk in log_models.chunks(CHUNK_SIZE) {
             let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
                 "INSERT INTO activation(activated_at, hostname, username, store_path, activation_type) ",
@@ -26,19 +35,110 @@ impl ActivationRepository {
             });
             // on conflict do nothing to avoid duplicate entries
             query_builder
-                .push(" ON CONFLICT (hostname, username, activated_at, store_path, activation_type) DO NOTHING");
-            let query = query_builder.build();
-            let res = query.execute(&mut **transaction).await?;
-            i += res.rows_affected();
+                .push(" ON CONFLICT (hostname, username, activated_at, store_path, activation_type) DO NOTHING RETURNING activation_id");
+            let query = query_builder.build_query_scalar::<i64>();
+            inserted_ids.extend(query.fetch_all(&mut **transaction).await?);
         }
-        Ok(i)
+        Ok(inserted_ids)
+    }
+
+    /// Tells every server listening on `channel` which hosts got the activations,
+    /// once the transaction commits.
+    pub(crate) async fn notify_hosts(
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        channel: &str,
+        activation_ids: &[i64],
+    ) -> Result<(), RetError> {
+        // pg_notify returns void, which the query macros cannot decode
+        sqlx::query(
+            r#"
+SELECT pg_notify($1, h.hostname)::text
+FROM (SELECT DISTINCT hostname FROM activation WHERE activation_id = ANY($2)) h
+            "#,
+        )
+        .bind(channel)
+        .bind(activation_ids)
+        .execute(&mut **transaction)
+        .await?;
+        Ok(())
+    }
+
+    /// The latest activation time of each of the hosts that has one.
+    pub(crate) async fn latest_activated_at(
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        hostnames: &[String],
+    ) -> sqlx::Result<HashMap<String, DateTime<Utc>>> {
+        let rows = sqlx::query!(
+            r#"
+SELECT hostname, MAX(activated_at) AS "activated_at!"
+FROM activation
+WHERE hostname = ANY($1)
+GROUP BY hostname;
+            "#,
+            hostnames,
+        )
+        .fetch_all(&mut **transaction)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.hostname, row.activated_at))
+            .collect())
+    }
+
+    /// Deploys of store paths CI never linked to a commit, newest first. Only the
+    /// activations in `activation_ids` if given, `limit` None returns all of them.
+    pub async fn get_unknown_provenance(
+        pool: &Pool<Postgres>,
+        activation_ids: Option<&[i64]>,
+        limit: Option<i64>,
+        offset: i64,
+    ) -> sqlx::Result<Vec<Activation>> {
+        let deploy_types: Vec<String> = DEPLOY_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let rows = sqlx::query!(
+            r#"
+SELECT a.activation_id, a.activated_at, a.username, a.hostname, a.store_path, a.activation_type
+FROM activation a
+WHERE a.activation_type = ANY($1)
+  AND ($2::bigint[] IS NULL OR a.activation_id = ANY($2))
+  AND NOT EXISTS (SELECT 1 FROM nix_git_link n WHERE n.store_path = a.store_path)
+ORDER BY a.activated_at DESC, a.activation_id DESC
+LIMIT $3 OFFSET $4;
+            "#,
+            &deploy_types,
+            activation_ids as Option<&[i64]>,
+            limit,
+            offset,
+        )
+        .map(|record| Activation {
+            id: record.activation_id,
+            core: ActivationCore {
+                activated_at: record.activated_at,
+                username: record.username,
+                store_path: record.store_path,
+                activation_type: record.activation_type,
+                hostname: record.hostname,
+                revision: None,
+            },
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
     }
     pub async fn get_logs_by_hostname(
         pool: &Pool<Postgres>,
         hostname: &str,
-    ) -> sqlx::Result<Vec<ActivationWithRevision>> {
-        let rows = sqlx::query_as!(
-            ActivationWithRevision,
+        branch_precedence: &BranchPrecedence,
+    ) -> sqlx::Result<Vec<HistoryEntry>> {
+        let deploy_types: Vec<String> = DEPLOY_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        // an activation is a rollback when the host had its store path before and the
+        // commit of it was linked before the commit of the activation preceding it
+        let rows = sqlx::query!(
             r#"
 WITH wanted AS (
   SELECT DISTINCT a.store_path
@@ -47,23 +147,396 @@ WITH wanted AS (
 ),
 best AS (
   SELECT DISTINCT ON (n.store_path)
-         n.store_path, n.commit_hash, n.branch
+         n.store_path, n.commit_hash, n.branch, n.linked_at
   FROM nix_git_link n
   JOIN wanted w ON w.store_path = n.store_path
-  ORDER BY n.store_path, (n.branch='master') DESC, n.linked_at ASC NULLS LAST
+  ORDER BY n.store_path, (SELECT MIN(p.rank) FROM unnest($3::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at ASC NULLS LAST
+),
+deployed AS (
+  SELECT a.activation_id, a.store_path, b.linked_at,
+         ROW_NUMBER() OVER (PARTITION BY a.store_path ORDER BY a.activated_at) > 1 AS seen_before,
+         LAG(a.store_path) OVER w AS prev_store_path,
+         LAG(b.linked_at) OVER w AS prev_linked_at
+  FROM activation a
+  LEFT JOIN best b ON b.store_path = a.store_path
+  WHERE a.hostname = $1 AND a.activation_type = ANY($2)
+  WINDOW w AS (ORDER BY a.activated_at)
 )
 SELECT a.activation_id, a.activated_at, a.username, a.hostname, a.store_path,
-       a.activation_type, b.commit_hash AS "commit_hash?", b.branch AS "branch?"
+       a.activation_type, b.commit_hash AS "commit_hash?", b.branch AS "branch?",
+       COALESCE(d.seen_before AND d.store_path <> d.prev_store_path
+                AND d.linked_at < d.prev_linked_at, FALSE) AS "is_rollback!"
 FROM activation a
 LEFT JOIN best b ON b.store_path = a.store_path
+LEFT JOIN deployed d ON d.activation_id = a.activation_id
 WHERE a.hostname = $1
 ORDER BY a.activated_at DESC;
             "#,
-            hostname
+            hostname,
+            &deploy_types,
+            branch_precedence.like_patterns(),
         )
+        .map(|record| HistoryEntry {
+            activation: ActivationWithRevision {
+                activation_id: record.activation_id,
+                activated_at: record.activated_at,
+                username: record.username,
+                store_path: record.store_path,
+                activation_type: record.activation_type,
+                hostname: record.hostname,
+                commit_hash: record.commit_hash,
+                branch: record.branch,
+            },
+            is_rollback: record.is_rollback,
+        })
         .fetch_all(pool)
         .await?;
 
         Ok(rows)
     }
+
+    /// Number of rollbacks among the latest `window` deploys of each host, hosts
+    /// without any are left out.
+    pub async fn count_rollbacks_per_host(
+        pool: &Pool<Postgres>,
+        hostname: Option<&str>,
+        branch_precedence: &BranchPrecedence,
+        window: i64,
+    ) -> sqlx::Result<HashMap<String, i64>> {
+        let deploy_types: Vec<String> = DEPLOY_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let rows = sqlx::query!(
+            r#"
+WITH recent AS (
+  SELECT r.hostname, r.store_path, r.activated_at
+  FROM host h
+  CROSS JOIN LATERAL (
+    SELECT a.hostname, a.store_path, a.activated_at
+    FROM activation a
+    WHERE a.hostname = h.hostname AND a.activation_type = ANY($1)
+    ORDER BY a.activated_at DESC
+    LIMIT $3
+  ) r
+  WHERE $4::text IS NULL OR h.hostname = $4
+),
+best AS (
+  SELECT DISTINCT ON (n.store_path)
+         n.store_path, n.linked_at
+  FROM nix_git_link n
+  WHERE n.store_path IN (SELECT store_path FROM recent)
+  ORDER BY n.store_path, (SELECT MIN(p.rank) FROM unnest($2::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at ASC NULLS LAST
+),
+deployed AS (
+  SELECT a.hostname, a.store_path, b.linked_at,
+         ROW_NUMBER() OVER (PARTITION BY a.hostname, a.store_path ORDER BY a.activated_at) > 1 AS seen_before,
+         LAG(a.store_path) OVER w AS prev_store_path,
+         LAG(b.linked_at) OVER w AS prev_linked_at
+  FROM recent a
+  LEFT JOIN best b ON b.store_path = a.store_path
+  WINDOW w AS (PARTITION BY a.hostname ORDER BY a.activated_at)
+)
+SELECT d.hostname, COUNT(*) AS "rollbacks!"
+FROM deployed d
+WHERE d.seen_before AND d.store_path <> d.prev_store_path AND d.linked_at < d.prev_linked_at
+GROUP BY d.hostname;
+            "#,
+            &deploy_types,
+            branch_precedence.like_patterns(),
+            window,
+            hostname,
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|record| (record.hostname, record.rollbacks))
+            .collect())
+    }
+
+    /// The latest activations, of one host or the hosts with a metadata value if given.
+    pub async fn get_recent(
+        pool: &Pool<Postgres>,
+        hostname: Option<&str>,
+        group: Option<(&str, &str)>,
+        limit: i64,
+        branch_precedence: &BranchPrecedence,
+    ) -> sqlx::Result<Vec<ActivationWithRevision>> {
+        let (group_key, group_value) = group.unzip();
+        let rows = sqlx::query_as!(
+            ActivationWithRevision,
+            r#"
+SELECT a.activation_id, a.activated_at, a.username, a.hostname, a.store_path,
+       a.activation_type, best.commit_hash AS "commit_hash?", best.branch AS "branch?"
+FROM activation a
+JOIN host h ON h.hostname = a.hostname
+LEFT JOIN LATERAL (
+  SELECT n.commit_hash, n.branch FROM nix_git_link n
+  WHERE n.store_path = a.store_path
+  ORDER BY (SELECT MIN(p.rank) FROM unnest($1::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at ASC
+  LIMIT 1
+) best ON TRUE
+WHERE ($2::text IS NULL OR a.hostname = $2)
+  AND ($3::text IS NULL OR h.metadata ->> $3 = $4)
+ORDER BY a.activated_at DESC, a.activation_id DESC
+LIMIT $5;
+            "#,
+            branch_precedence.like_patterns(),
+            hostname,
+            group_key,
+            group_value,
+            limit,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// The activations with the given ids, those that do not exist are left out.
+    pub async fn get_by_ids(
+        pool: &Pool<Postgres>,
+        activation_ids: &[i64],
+        branch_precedence: &BranchPrecedence,
+    ) -> sqlx::Result<Vec<ActivationWithRevision>> {
+        let rows = sqlx::query_as!(
+            ActivationWithRevision,
+            r#"
+SELECT a.activation_id, a.activated_at, a.username, a.hostname, a.store_path,
+       a.activation_type, best.commit_hash AS "commit_hash?", best.branch AS "branch?"
+FROM activation a
+LEFT JOIN LATERAL (
+  SELECT n.commit_hash, n.branch FROM nix_git_link n
+  WHERE n.store_path = a.store_path
+  ORDER BY (SELECT MIN(p.rank) FROM unnest($1::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at ASC
+  LIMIT 1
+) best ON TRUE
+WHERE a.activation_id = ANY($2);
+            "#,
+            branch_precedence.like_patterns(),
+            activation_ids,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Activations of the feed page, consecutive activations of one store path
+    /// across hosts count as one entry when paginating.
+    pub async fn get_activity(
+        pool: &Pool<Postgres>,
+        filter: &ActivityFilter,
+        entry_limit: i64,
+        entry_offset: i64,
+        branch_precedence: &BranchPrecedence,
+    ) -> sqlx::Result<Vec<ActivityRow>> {
+        let (group_key, group_value) = filter.group.clone().unzip();
+        let rows = sqlx::query_as!(
+            ActivityRow,
+            r#"
+WITH filtered AS (
+  SELECT a.activation_id, a.activated_at, a.username, a.hostname, a.store_path,
+         a.activation_type, best.commit_hash, best.branch
+  FROM activation a
+  JOIN host h ON h.hostname = a.hostname
+  LEFT JOIN LATERAL (
+    SELECT n.commit_hash, n.branch FROM nix_git_link n
+    WHERE n.store_path = a.store_path
+    ORDER BY (SELECT MIN(p.rank) FROM unnest($1::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at ASC
+    LIMIT 1
+  ) best ON TRUE
+  WHERE ($2::text IS NULL OR h.metadata ->> $2 = $3)
+    AND ($4::text IS NULL OR a.username = $4)
+    AND ($5::text IS NULL OR best.branch = $5)
+    AND ($6::text IS NULL OR a.activation_type = $6)
+),
+islands AS (
+  SELECT f.*,
+         ROW_NUMBER() OVER (ORDER BY f.activated_at DESC, f.activation_id DESC)
+           - ROW_NUMBER() OVER (PARTITION BY f.store_path ORDER BY f.activated_at DESC, f.activation_id DESC) AS island
+  FROM filtered f
+),
+entries AS (
+  SELECT i.store_path, i.island, MAX(i.activated_at) AS latest
+  FROM islands i
+  GROUP BY i.store_path, i.island
+  ORDER BY latest DESC, i.store_path, i.island
+  LIMIT $7 OFFSET $8
+)
+SELECT i.activation_id AS "activation_id!", i.activated_at AS "activated_at!", i.username AS "username!",
+       i.hostname AS "hostname!", i.store_path AS "store_path!", i.activation_type AS "activation_type!",
+       i.commit_hash AS "commit_hash?", i.branch AS "branch?", i.island AS "island!"
+FROM islands i
+JOIN entries e ON e.store_path = i.store_path AND e.island = i.island
+ORDER BY e.latest DESC, e.store_path, e.island, i.activated_at DESC, i.activation_id DESC;
+            "#,
+            branch_precedence.like_patterns(),
+            group_key,
+            group_value,
+            filter.username,
+            filter.branch,
+            filter.activation_type,
+            entry_limit,
+            entry_offset,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::repository::fixtures::{activate, at, insert_hosts, link};
+
+    async fn history(pool: &Pool<Postgres>) -> Vec<(String, String, bool)> {
+        let mut entries =
+            ActivationRepository::get_logs_by_hostname(pool, "a", &BranchPrecedence::new(&[]))
+                .await
+                .unwrap();
+        entries.reverse();
+        entries
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.activation.store_path,
+                    entry.activation.activation_type,
+                    entry.is_rollback,
+                )
+            })
+            .collect()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_rollbacks(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b"]).await;
+        link(
+            &pool,
+            &[
+                (0, "/nix/store/a", "c1"),
+                (10, "/nix/store/b", "c2"),
+                (20, "/nix/store/c", "c3"),
+            ],
+        )
+        .await;
+        activate(
+            &pool,
+            "a",
+            &[
+                (0, "/nix/store/a", "switch"),
+                (1, "/nix/store/b", "switch"),
+                (2, "/nix/store/a", "dry-activate"),
+                (3, "/nix/store/b", "test"),
+                (4, "/nix/store/a", "switch"),
+                (5, "/nix/store/a", "switch"),
+                (6, "/nix/store/c", "switch"),
+                (7, "/nix/store/b", "switch"),
+                (8, "/nix/store/x", "switch"),
+                (9, "/nix/store/c", "switch"),
+            ],
+        )
+        .await;
+        activate(
+            &pool,
+            "b",
+            &[(0, "/nix/store/b", "switch"), (1, "/nix/store/a", "switch")],
+        )
+        .await;
+
+        let rollback = |store_path: &str, activation_type: &str, is_rollback: bool| {
+            (
+                store_path.to_string(),
+                activation_type.to_string(),
+                is_rollback,
+            )
+        };
+        assert_eq!(
+            history(&pool).await,
+            vec![
+                rollback("/nix/store/a", "switch", false),
+                rollback("/nix/store/b", "switch", false),
+                // a dry run deploys nothing
+                rollback("/nix/store/a", "dry-activate", false),
+                // testing what already runs changes nothing
+                rollback("/nix/store/b", "test", false),
+                // back to an older commit than the test before it
+                rollback("/nix/store/a", "switch", true),
+                rollback("/nix/store/a", "switch", false),
+                rollback("/nix/store/c", "switch", false),
+                // a path the host had before, after a newer one
+                rollback("/nix/store/b", "switch", true),
+                // unknown provenance
+                rollback("/nix/store/x", "switch", false),
+                rollback("/nix/store/c", "switch", false),
+            ]
+        );
+
+        let count = |window| {
+            let pool = pool.clone();
+            async move {
+                ActivationRepository::count_rollbacks_per_host(
+                    &pool,
+                    None,
+                    &BranchPrecedence::new(&[]),
+                    window,
+                )
+                .await
+                .unwrap()
+            }
+        };
+        // host b went to an older commit, but never had it before
+        assert_eq!(count(100).await, HashMap::from([("a".to_string(), 2)]));
+        // only b at minute 7 had its path before within the latest seven deploys
+        assert_eq!(count(7).await, HashMap::from([("a".to_string(), 1)]));
+        assert_eq!(count(3).await, HashMap::new());
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_unknown_provenance(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b"]).await;
+        link(&pool, &[(0, "/nix/store/a", "c1")]).await;
+        let a = activate(
+            &pool,
+            "a",
+            &[
+                (1, "/nix/store/a", "switch"),
+                (2, "/nix/store/x", "switch"),
+                // never deployed
+                (3, "/nix/store/y", "dry-activate"),
+                (4, "/nix/store/y", "test"),
+                (5, "/nix/store/y", "boot"),
+            ],
+        )
+        .await;
+        let b = activate(&pool, "b", &[(6, "/nix/store/z", "switch")]).await;
+
+        let unknown = |ids: Option<Vec<i64>>, limit, offset| {
+            let pool = pool.clone();
+            async move {
+                ActivationRepository::get_unknown_provenance(&pool, ids.as_deref(), limit, offset)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|activation| activation.id)
+                    .collect::<Vec<i64>>()
+            }
+        };
+        assert_eq!(unknown(None, None, 0).await, vec![b[0], a[4], a[3], a[1]]);
+        assert_eq!(unknown(None, Some(2), 1).await, vec![a[4], a[3]]);
+        assert_eq!(
+            unknown(Some(a.clone()), None, 0).await,
+            vec![a[4], a[3], a[1]]
+        );
+
+        let mut tx = pool.begin().await.unwrap();
+        let latest =
+            ActivationRepository::latest_activated_at(&mut tx, &["a".to_string(), "c".to_string()])
+                .await
+                .unwrap();
+        assert_eq!(latest, HashMap::from([("a".to_string(), at(5))]));
+    }
 }
@@ -0,0 +1,203 @@
+use sqlx::{Pool, Postgres};
+
+use crate::shared::model::annotation::{AnnotationContent, AnnotationModel};
+
+#[derive(Debug, Clone)]
+pub struct AnnotationRepository;
+
+impl AnnotationRepository {
+    /// None if the host does not exist, or `activation_id` is not one of its activations.
+    pub async fn insert(
+        pool: &Pool<Postgres>,
+        hostname: &str,
+        activation_id: Option<i64>,
+        content: &AnnotationContent,
+    ) -> sqlx::Result<Option<AnnotationModel>> {
+        sqlx::query_as!(
+            AnnotationModel,
+            r#"
+INSERT INTO annotation (hostname, activation_id, author, body, expires_at)
+SELECT h.hostname, $2, $3, $4, $5
+FROM host h
+WHERE h.hostname = $1
+  AND ($2::bigint IS NULL OR EXISTS (
+    SELECT 1 FROM activation a WHERE a.activation_id = $2 AND a.hostname = h.hostname
+  ))
+RETURNING annotation_id, hostname, activation_id, author, body, created_at, updated_at, expires_at;
+            "#,
+            hostname,
+            activation_id,
+            content.author,
+            content.body,
+            content.expires_at,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn update(
+        pool: &Pool<Postgres>,
+        annotation_id: i64,
+        content: &AnnotationContent,
+    ) -> sqlx::Result<Option<AnnotationModel>> {
+        sqlx::query_as!(
+            AnnotationModel,
+            r#"
+UPDATE annotation
+SET author = $2, body = $3, expires_at = $4, updated_at = NOW()
+WHERE annotation_id = $1
+RETURNING annotation_id, hostname, activation_id, author, body, created_at, updated_at, expires_at;
+            "#,
+            annotation_id,
+            content.author,
+            content.body,
+            content.expires_at,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Expires the note now, unless it already has.
+    pub async fn expire(
+        pool: &Pool<Postgres>,
+        annotation_id: i64,
+    ) -> sqlx::Result<Option<AnnotationModel>> {
+        sqlx::query_as!(
+            AnnotationModel,
+            r#"
+UPDATE annotation
+SET expires_at = LEAST(COALESCE(expires_at, NOW()), NOW()), updated_at = NOW()
+WHERE annotation_id = $1
+RETURNING annotation_id, hostname, activation_id, author, body, created_at, updated_at, expires_at;
+            "#,
+            annotation_id,
+        )
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Notes on a host and its activations, newest first.
+    pub async fn get_by_hostname(
+        pool: &Pool<Postgres>,
+        hostname: &str,
+    ) -> sqlx::Result<Vec<AnnotationModel>> {
+        sqlx::query_as!(
+            AnnotationModel,
+            r#"
+SELECT annotation_id, hostname, activation_id, author, body, created_at, updated_at, expires_at
+FROM annotation
+WHERE hostname = $1
+ORDER BY created_at DESC, annotation_id DESC;
+            "#,
+            hostname,
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Notes on hosts, not on single activations, that have not expired, newest first.
+    pub async fn get_active_host_notes(
+        pool: &Pool<Postgres>,
+    ) -> sqlx::Result<Vec<AnnotationModel>> {
+        sqlx::query_as!(
+            AnnotationModel,
+            r#"
+SELECT annotation_id, hostname, activation_id, author, body, created_at, updated_at, expires_at
+FROM annotation
+WHERE activation_id IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+ORDER BY created_at DESC, annotation_id DESC;
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use sqlx::{Pool, Postgres};
+
+    use super::AnnotationRepository;
+    use crate::{
+        server::repository::fixtures::{activate, insert_hosts},
+        shared::model::annotation::AnnotationContent,
+    };
+
+    fn content(expires_at: Option<chrono::DateTime<Utc>>) -> AnnotationContent {
+        AnnotationContent {
+            author: "alice".to_string(),
+            body: "disk is flaky".to_string(),
+            expires_at,
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_insert_checks_the_activation_host(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b"]).await;
+        let a = activate(&pool, "a", &[(0, "/nix/store/a", "switch")]).await;
+        let b = activate(&pool, "b", &[(0, "/nix/store/b", "switch")]).await;
+
+        let on_a = AnnotationRepository::insert(&pool, "a", Some(a[0]), &content(None))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(on_a.activation_id, Some(a[0]));
+        assert!(
+            AnnotationRepository::insert(&pool, "a", Some(b[0]), &content(None))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            AnnotationRepository::insert(&pool, "a", Some(i64::MAX), &content(None))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            AnnotationRepository::insert(&pool, "c", None, &content(None))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_expire_keeps_an_earlier_expiry(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a"]).await;
+        let past = Utc::now() - Duration::days(1);
+        let future = Utc::now() + Duration::days(1);
+        let mut ids = Vec::new();
+        for expires_at in [None, Some(past), Some(future)] {
+            let annotation = AnnotationRepository::insert(&pool, "a", None, &content(expires_at))
+                .await
+                .unwrap()
+                .unwrap();
+            ids.push(annotation.annotation_id);
+        }
+
+        let before = Utc::now();
+        let mut expired = Vec::new();
+        for id in ids {
+            let annotation = AnnotationRepository::expire(&pool, id)
+                .await
+                .unwrap()
+                .unwrap();
+            expired.push(annotation.expires_at.unwrap());
+        }
+        let after = Utc::now();
+
+        assert!(expired[0] >= before && expired[0] <= after);
+        assert_eq!(expired[1].timestamp_micros(), past.timestamp_micros());
+        assert!(expired[2] >= before && expired[2] <= after);
+        assert!(
+            AnnotationRepository::expire(&pool, i64::MAX)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
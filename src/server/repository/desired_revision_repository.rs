@@ -0,0 +1,84 @@
+use sqlx::{Pool, Postgres};
+
+use crate::shared::model::desired_revision::{DesiredRevisionModel, DesiredTarget};
+
+#[derive(Debug, Clone)]
+pub struct DesiredRevisionRepository;
+
+impl DesiredRevisionRepository {
+    /// Sets the target of a group, replacing the one it had.
+    pub async fn upsert(
+        pool: &Pool<Postgres>,
+        desired: &DesiredRevisionModel,
+    ) -> sqlx::Result<DesiredRevisionModel> {
+        let (commit_hash, store_path) = match &desired.target {
+            DesiredTarget::Commit(commit_hash) => (Some(commit_hash.as_str()), None),
+            DesiredTarget::StorePath(store_path) => (None, Some(store_path.as_str())),
+        };
+        let record = sqlx::query!(
+            r#"
+INSERT INTO desired_revision (grouping_key, group_value, commit_hash, store_path, deadline, author, set_at)
+VALUES ($1, $2, $3, $4, $5, $6, NOW())
+ON CONFLICT (grouping_key, group_value) DO UPDATE
+SET commit_hash = EXCLUDED.commit_hash, store_path = EXCLUDED.store_path,
+    deadline = EXCLUDED.deadline, author = EXCLUDED.author, set_at = EXCLUDED.set_at
+RETURNING set_at;
+            "#,
+            desired.grouping_key,
+            desired.group_value,
+            commit_hash,
+            store_path,
+            desired.deadline,
+            desired.author,
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(DesiredRevisionModel {
+            set_at: record.set_at,
+            ..desired.clone()
+        })
+    }
+
+    /// Whether the group had a target.
+    pub async fn delete(
+        pool: &Pool<Postgres>,
+        grouping_key: &str,
+        group_value: &str,
+    ) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM desired_revision WHERE grouping_key = $1 AND group_value = $2",
+            grouping_key,
+            group_value,
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_all(pool: &Pool<Postgres>) -> sqlx::Result<Vec<DesiredRevisionModel>> {
+        let rows = sqlx::query!(
+            r#"
+SELECT grouping_key, group_value, commit_hash, store_path, deadline, author, set_at
+FROM desired_revision
+ORDER BY grouping_key, group_value;
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|record| DesiredRevisionModel {
+                grouping_key: record.grouping_key,
+                group_value: record.group_value,
+                // the table checks that exactly one of them is set
+                target: match record.commit_hash {
+                    Some(commit_hash) => DesiredTarget::Commit(commit_hash),
+                    None => DesiredTarget::StorePath(record.store_path.unwrap_or_default()),
+                },
+                deadline: record.deadline,
+                author: record.author,
+                set_at: record.set_at,
+            })
+            .collect())
+    }
+}
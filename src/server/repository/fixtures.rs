@@ -0,0 +1,112 @@
+//! Rows for the repository tests that run against postgres. Times are minutes
+//! after noon on 2026-01-05 UTC.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::repository::{
+        activation_repository::ActivationRepository, host_repository::HostRepository,
+        nix_git_link_repository::NixGitLinkRepository, store_path_repository::StorePathRepository,
+    },
+    shared::model::{
+        activation::{ActivationCore, NewActivation},
+        host::HostModel,
+        nix_git_link::NixGitLinkModel,
+        revision::RevisionModel,
+    },
+};
+
+pub(crate) fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap() + chrono::Duration::minutes(minute.into())
+}
+
+pub(crate) fn host(hostname: &str) -> HostModel {
+    HostModel {
+        hostname: hostname.to_string(),
+        host_url: hostname.to_string(),
+        metadata: HashMap::from([("env".to_string(), "prod".to_string())]),
+    }
+}
+
+pub(crate) async fn insert_hosts(pool: &Pool<Postgres>, hostnames: &[&str]) {
+    let hosts: Vec<HostModel> = hostnames.iter().map(|hostname| host(hostname)).collect();
+    let mut tx = pool.begin().await.unwrap();
+    HostRepository::bulk_insert_hosts(&mut tx, &hosts)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+}
+
+/// Activations of a host, each `(minute, store path, activation type)`.
+pub(crate) fn new_activations(
+    hostname: &str,
+    activations: &[(u32, &str, &str)],
+) -> Vec<NewActivation> {
+    activations
+        .iter()
+        .map(|(minute, store_path, activation_type)| NewActivation {
+            core: ActivationCore {
+                activated_at: at(*minute),
+                username: "root".to_string(),
+                store_path: store_path.to_string(),
+                activation_type: activation_type.to_string(),
+                hostname: hostname.to_string(),
+                revision: None,
+            },
+        })
+        .collect()
+}
+
+/// Inserts activations as [`new_activations`] builds them. Returns the ids
+/// of those not seen before, in order.
+pub(crate) async fn activate(
+    pool: &Pool<Postgres>,
+    hostname: &str,
+    activations: &[(u32, &str, &str)],
+) -> Vec<i64> {
+    let activations = new_activations(hostname, activations);
+    let store_paths: Vec<&str> = activations
+        .iter()
+        .map(|activation| activation.core.store_path.as_str())
+        .collect();
+    let mut tx = pool.begin().await.unwrap();
+    StorePathRepository::bulk_insert_store_paths(&mut tx, &store_paths)
+        .await
+        .unwrap();
+    let ids = ActivationRepository::insert_many(&mut tx, &activations)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    ids
+}
+
+/// Links of store paths to commits on master, each `(minute, store path, commit)`.
+pub(crate) async fn link(pool: &Pool<Postgres>, links: &[(u32, &str, &str)]) {
+    let links: Vec<NixGitLinkModel> = links
+        .iter()
+        .map(|(minute, store_path, commit_hash)| NixGitLinkModel {
+            nix_store_path: store_path.to_string(),
+            revision: RevisionModel {
+                commit_hash: commit_hash.to_string(),
+                branch: "master".to_string(),
+            },
+            linked_at: at(*minute),
+            hostname: None,
+        })
+        .collect();
+    let store_paths: Vec<&str> = links
+        .iter()
+        .map(|link| link.nix_store_path.as_str())
+        .collect();
+    let mut tx = pool.begin().await.unwrap();
+    StorePathRepository::bulk_insert_store_paths(&mut tx, &store_paths)
+        .await
+        .unwrap();
+    NixGitLinkRepository::create_many(&mut tx, links)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+}
@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, HashMap};
+
+use sqlx::{Pool, Postgres, QueryBuilder};
+
+use crate::{server::RetError, shared::model::nix_git_link::FlakeInputModel};
+
+#[derive(Debug, Clone)]
+pub struct FlakeInputRepository;
+
+impl FlakeInputRepository {
+    /// Inserts the inputs, a store path reported again keeps the latest revisions.
+    /// Within `inputs` the last revision of an input of a store path wins.
+    pub async fn create_many(
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        inputs: &[FlakeInputModel],
+    ) -> Result<u64, RetError> {
+        // one statement cannot update the same row twice
+        let inputs: Vec<&FlakeInputModel> = inputs
+            .iter()
+            .map(|input| {
+                (
+                    (input.store_path.as_str(), input.input_name.as_str()),
+                    input,
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect();
+        const CHUNK_SIZE: usize = 1000;
+        let mut i = 0;
+        for chunk in inputs.chunks(CHUNK_SIZE) {
+            let mut query_builder: QueryBuilder<Postgres> =
+                QueryBuilder::new("INSERT INTO flake_input(store_path, input_name, revision) ");
+            query_builder.push_values(chunk.iter(), |mut b, rec| {
+                b.push_bind(&rec.store_path)
+                    .push_bind(&rec.input_name)
+                    .push_bind(&rec.revision);
+            });
+            query_builder.push(
+                " ON CONFLICT (store_path, input_name) DO UPDATE SET revision = EXCLUDED.revision",
+            );
+            let res = query_builder.build().execute(&mut **tx).await?;
+            i += res.rows_affected();
+        }
+        Ok(i)
+    }
+
+    /// The inputs of every store path that has any, by store path and input name.
+    pub async fn get_by_store_paths(
+        pool: &Pool<Postgres>,
+        store_paths: &[String],
+    ) -> Result<HashMap<String, BTreeMap<String, String>>, RetError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT store_path, input_name, revision FROM flake_input
+                WHERE store_path = ANY($1)
+            "#,
+            store_paths,
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut inputs: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        for row in rows {
+            inputs
+                .entry(row.store_path)
+                .or_default()
+                .insert(row.input_name, row.revision);
+        }
+        Ok(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::repository::store_path_repository::StorePathRepository;
+
+    fn input(store_path: &str, input_name: &str, revision: &str) -> FlakeInputModel {
+        FlakeInputModel {
+            store_path: store_path.to_string(),
+            input_name: input_name.to_string(),
+            revision: revision.to_string(),
+        }
+    }
+
+    async fn insert(pool: &Pool<Postgres>, inputs: &[FlakeInputModel]) {
+        let store_paths: Vec<&str> = inputs.iter().map(|i| i.store_path.as_str()).collect();
+        let mut tx = pool.begin().await.unwrap();
+        StorePathRepository::bulk_insert_store_paths(&mut tx, &store_paths)
+            .await
+            .unwrap();
+        FlakeInputRepository::create_many(&mut tx, inputs)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_inputs_by_store_path(pool: Pool<Postgres>) {
+        insert(
+            &pool,
+            &[
+                input("/nix/store/a", "nixpkgs", "1a2b3c"),
+                input("/nix/store/a", "home-manager", "9f8e7d"),
+                input("/nix/store/b", "nixpkgs", "4d5e6f"),
+            ],
+        )
+        .await;
+        // reported again with a new revision
+        insert(&pool, &[input("/nix/store/b", "nixpkgs", "7a8b9c")]).await;
+
+        let store_paths = ["/nix/store/a", "/nix/store/b", "/nix/store/c"].map(String::from);
+        let inputs = FlakeInputRepository::get_by_store_paths(&pool, &store_paths)
+            .await
+            .unwrap();
+        let revisions = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(name, rev)| (name.to_string(), rev.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            inputs,
+            HashMap::from([
+                (
+                    "/nix/store/a".to_string(),
+                    revisions(&[("home-manager", "9f8e7d"), ("nixpkgs", "1a2b3c")])
+                ),
+                (
+                    "/nix/store/b".to_string(),
+                    revisions(&[("nixpkgs", "7a8b9c")])
+                ),
+            ])
+        );
+    }
+}
@@ -1,19 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::shared::model::{
-    activation::{Activation, ActivationWithRevision},
-    host::{HostModel, HostWithLatestLog},
+    activation::{
+        Activation, ActivationWithRevision, NEXT_BOOT_ACTIVATION_TYPES, RUNNING_ACTIVATION_TYPES,
+        TRANSIENT_ACTIVATION_TYPES,
+    },
+    host::{HostMetricsRow, HostModel, HostWithState},
+    rollout::RolloutHost,
 };
 use sqlx::{Pool, Postgres, QueryBuilder};
 
-use crate::server::custom_error::RetError;
+use crate::server::{branch_precedence::BranchPrecedence, custom_error::RetError};
 
 #[derive(Debug, Clone)]
 pub struct HostRepository {}
 
 impl HostRepository {
     pub async fn bulk_insert_hosts(
-        pool: &Pool<Postgres>,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
         hosts: &[HostModel],
     ) -> Result<u64, sqlx::Error> {
         const CHUNK_SIZE: usize = 500; // rows (hosts) per INSERT
@@ -37,12 +41,32 @@ impl HostRepository {
             // on conflict do nothing to avoid duplicate entries
             query_builder.push(" ON CONFLICT (hostname) DO UPDATE SET host_url = EXCLUDED.host_url, metadata = EXCLUDED.metadata, updated_at = NOW(), created_at = host.created_at");
             let query = query_builder.build();
-            let res = query.execute(pool).await?;
+            let res = query.execute(&mut **tx).await?;
             rows_inserted += res.rows_affected();
         }
         Ok(rows_inserted)
     }
 
+    /// Retires a host, it is no longer expected to be scraped, or brings it
+    /// back. False if there is no such host.
+    pub async fn set_retired(
+        pool: &Pool<Postgres>,
+        hostname: &str,
+        retired: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE host SET retired_at = CASE WHEN $2 THEN COALESCE(retired_at, NOW()) END
+                WHERE hostname = $1
+            "#,
+            hostname,
+            retired,
+        )
+        .execute(pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
     pub async fn get_host_from_hostname(
         pool: &Pool<Postgres>,
         hostname: String,
@@ -68,9 +92,72 @@ impl HostRepository {
         Ok(result)
     }
 
-    pub async fn get_all_hosts_with_latest_activation(
+    /// Every host with its state, or only `hostname` when given.
+    pub async fn get_hosts_with_state(
         pool: &Pool<Postgres>,
-    ) -> Result<Vec<HostWithLatestLog>, RetError> {
+        hostname: Option<&str>,
+        branch_precedence: &BranchPrecedence,
+    ) -> Result<Vec<HostWithState>, RetError> {
+        let running = Self::get_latest_activations_of_types(
+            pool,
+            &RUNNING_ACTIVATION_TYPES,
+            hostname,
+            branch_precedence,
+        )
+        .await?;
+        let next_boot = Self::get_latest_activations_of_types(
+            pool,
+            &NEXT_BOOT_ACTIVATION_TYPES,
+            hostname,
+            branch_precedence,
+        )
+        .await?;
+        let tests = Self::get_latest_activations_of_types(
+            pool,
+            &TRANSIENT_ACTIVATION_TYPES,
+            hostname,
+            branch_precedence,
+        )
+        .await?;
+        let hosts = match hostname {
+            Some(hostname) => Self::get_host_from_hostname(pool, hostname.to_string())
+                .await?
+                .into_iter()
+                .collect(),
+            None => Self::get_all_hosts(pool).await?,
+        };
+
+        let find = |logs: &[Activation], hostname: &str| {
+            logs.iter()
+                .find(|log| log.core.hostname == hostname)
+                .cloned()
+        };
+        let mut result = Vec::new();
+        for host in hosts {
+            let host_with_state = HostWithState {
+                running: find(&running, &host.hostname),
+                next_boot: find(&next_boot, &host.hostname),
+                latest_test: find(&tests, &host.hostname),
+                rollbacks: 0,
+                latest_build: None,
+                running_inputs: BTreeMap::new(),
+                host,
+            };
+            result.push(host_with_state);
+        }
+        Ok(result)
+    }
+
+    /// The newest activation per host among the given activation types, of
+    /// every host or only `hostname`.
+    async fn get_latest_activations_of_types(
+        pool: &Pool<Postgres>,
+        activation_types: &[&str],
+        hostname: Option<&str>,
+        branch_precedence: &BranchPrecedence,
+    ) -> Result<Vec<Activation>, RetError> {
+        let activation_types: Vec<String> =
+            activation_types.iter().map(|t| t.to_string()).collect();
         let logs = sqlx::query_as!(
             ActivationWithRevision,
             r#"
@@ -80,36 +167,65 @@ SELECT
 DISTINCT ON (ac.hostname)
 ac.activation_id, ac.activated_at, ac.username, ac.store_path, ac.activation_type, ac.hostname
 FROM activation ac
+     WHERE ac.activation_type = ANY($1)
+       AND ($3::text IS NULL OR ac.hostname = $3)
      ORDER BY ac.hostname, ac.activated_at DESC
 )
 SELECT DISTINCT ON(l.hostname) l.activation_id, l.activated_at, l.username,
     l.store_path, l.activation_type, l.hostname, ngl.commit_hash AS "commit_hash?", ngl.branch AS "branch?"
     FROM latest l
     LEFT JOIN nix_git_link ngl ON ngl.store_path = l.store_path
-    ORDER BY l.hostname, ngl.branch = 'master' desc, ngl.linked_at asc
+    ORDER BY l.hostname, (SELECT MIN(p.rank) FROM unnest($2::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE ngl.branch LIKE p.pattern) ASC NULLS LAST, ngl.linked_at ASC
 ;
             "#,
+            &activation_types,
+            branch_precedence.like_patterns(),
+            hostname,
         )
         .fetch_all(pool)
         .await?;
-        let all_logs: Vec<Activation> = logs.into_iter().map(|el| el.into()).collect();
-        let hosts = Self::get_all_hosts(pool).await?;
+        Ok(logs.into_iter().map(|el| el.into()).collect())
+    }
 
-        let mut result = Vec::new();
-        for host in hosts {
-            let latest_log = all_logs
-                .iter()
-                .find(|log| log.core.hostname == host.hostname)
-                .cloned();
-
-            let host_with_latest_log = HostWithLatestLog {
-                host: host.clone(),
-                logs: latest_log,
-            };
-            result.push(host_with_latest_log);
-        }
-        Ok(result)
+    pub(crate) async fn get_host_metrics(
+        pool: &Pool<Postgres>,
+        branch_precedence: &BranchPrecedence,
+    ) -> Result<Vec<HostMetricsRow>, RetError> {
+        let running_types: Vec<String> = RUNNING_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let rows = sqlx::query_as!(
+            HostMetricsRow,
+            r#"
+WITH latest AS (
+SELECT
+DISTINCT ON (ac.hostname)
+ac.hostname, ac.activated_at, ac.store_path
+FROM activation ac
+     WHERE ac.activation_type = ANY($1)
+     ORDER BY ac.hostname, ac.activated_at DESC
+)
+SELECT h.hostname, h.retired_at IS NOT NULL AS "retired!",
+    l.activated_at AS "activated_at?", link.commit_hash AS "commit_hash?", link.branch AS "branch?"
+    FROM host h
+    LEFT JOIN latest l ON l.hostname = h.hostname
+    LEFT JOIN LATERAL (
+        SELECT ngl.commit_hash, ngl.branch FROM nix_git_link ngl
+        WHERE ngl.store_path = l.store_path
+        ORDER BY (SELECT MIN(p.rank) FROM unnest($2::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE ngl.branch LIKE p.pattern) ASC NULLS LAST, ngl.linked_at ASC
+        LIMIT 1
+    ) link ON TRUE
+    ORDER BY h.hostname;
+            "#,
+            &running_types,
+            branch_precedence.like_patterns(),
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
     }
+
     pub(crate) async fn get_all_hosts(pool: &Pool<Postgres>) -> Result<Vec<HostModel>, RetError> {
         let result = sqlx::query!(
             r#"
@@ -128,4 +244,123 @@ SELECT DISTINCT ON(l.hostname) l.activation_id, l.activated_at, l.username,
         .await?;
         Ok(result)
     }
+
+    /// Hosts that are not retired, with their first switch to one of `store_paths`.
+    pub(crate) async fn get_rollout_hosts(
+        pool: &Pool<Postgres>,
+        store_paths: &[String],
+    ) -> Result<Vec<RolloutHost>, RetError> {
+        let running_types: Vec<String> = RUNNING_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let result = sqlx::query!(
+            r#"
+WITH running AS (
+SELECT
+DISTINCT ON (ac.hostname)
+ac.hostname, ac.store_path
+FROM activation ac
+     WHERE ac.activation_type = ANY($2)
+     ORDER BY ac.hostname, ac.activated_at DESC
+),
+switched AS (
+SELECT ac.hostname, MIN(ac.activated_at) AS switched_at
+FROM activation ac
+     WHERE ac.store_path = ANY($1) AND ac.activation_type = ANY($2)
+     GROUP BY ac.hostname
+)
+SELECT h.hostname, h.metadata, s.switched_at AS "switched_at?",
+    COALESCE(r.store_path = ANY($1), FALSE) AS "running!"
+    FROM host h
+    LEFT JOIN switched s ON s.hostname = h.hostname
+    LEFT JOIN running r ON r.hostname = h.hostname
+    WHERE h.retired_at IS NULL
+    ORDER BY h.hostname;
+            "#,
+            store_paths,
+            &running_types,
+        )
+        .map(|record| RolloutHost {
+            hostname: record.hostname,
+            metadata: serde_json::from_value(record.metadata).unwrap_or(HashMap::from([(
+                "error".to_string(),
+                "nested json metadata is not allowed".to_string(),
+            )])),
+            switched_at: record.switched_at,
+            running: record.running,
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::repository::fixtures::{activate, insert_hosts};
+
+    async fn retired(pool: &Pool<Postgres>) -> Vec<(String, bool)> {
+        HostRepository::get_host_metrics(pool, &BranchPrecedence::new(&[]))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.hostname, row.retired))
+            .collect()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_latest_test_outlives_switch(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a"]).await;
+        activate(
+            &pool,
+            "a",
+            &[
+                (0, "/nix/store/a", "switch"),
+                (1, "/nix/store/b", "test"),
+                (2, "/nix/store/c", "dry-activate"),
+                (3, "/nix/store/d", "switch"),
+            ],
+        )
+        .await;
+        let hosts = HostRepository::get_hosts_with_state(&pool, None, &BranchPrecedence::new(&[]))
+            .await
+            .unwrap();
+        let store_path = |activation: &Option<Activation>| {
+            activation
+                .as_ref()
+                .map(|activation| activation.core.store_path.clone())
+        };
+        assert_eq!(store_path(&hosts[0].running).unwrap(), "/nix/store/d");
+        assert_eq!(store_path(&hosts[0].next_boot).unwrap(), "/nix/store/d");
+        assert_eq!(store_path(&hosts[0].latest_test).unwrap(), "/nix/store/b");
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_partial_bulk_insert_retires_nothing(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b", "c"]).await;
+        assert!(HostRepository::set_retired(&pool, "c", true).await.unwrap());
+
+        // a second scraper, or an old payload, with only some of the hosts
+        insert_hosts(&pool, &["a", "c"]).await;
+        assert_eq!(
+            retired(&pool).await,
+            vec![
+                ("a".to_string(), false),
+                ("b".to_string(), false),
+                ("c".to_string(), true)
+            ]
+        );
+
+        assert!(
+            HostRepository::set_retired(&pool, "c", false)
+                .await
+                .unwrap()
+        );
+        assert!(!HostRepository::set_retired(&pool, "d", true).await.unwrap());
+        assert!(retired(&pool).await.iter().all(|(_, retired)| !retired));
+    }
 }
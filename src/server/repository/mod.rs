@@ -1,4 +1,10 @@
 pub(crate) mod activation_repository;
+pub(crate) mod annotation_repository;
+pub(crate) mod desired_revision_repository;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub(crate) mod flake_input_repository;
 pub(crate) mod host_repository;
 pub(crate) mod nix_git_link_repository;
+pub(crate) mod package_repository;
 pub(crate) mod store_path_repository;
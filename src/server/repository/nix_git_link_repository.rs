@@ -1,6 +1,15 @@
-use sqlx::{Postgres, QueryBuilder};
+use std::collections::HashMap;
 
-use crate::{server::RetError, shared::model::nix_git_link::NixGitLinkModel};
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, QueryBuilder};
+
+use crate::{
+    server::{RetError, branch_precedence::BranchPrecedence},
+    shared::model::{
+        nix_git_link::{LatestBuild, NixGitLinkModel},
+        revision::RevisionModel,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct NixGitLinkRepository;
@@ -14,13 +23,14 @@ impl NixGitLinkRepository {
         let mut i = 0;
         for chunk in nix_git_links.chunks(CHUNK_SIZE) {
             let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-                "INSERT INTO nix_git_link(store_path, commit_hash, branch, linked_at) ",
+                "INSERT INTO nix_git_link(store_path, commit_hash, branch, linked_at, hostname) ",
             );
             query_builder.push_values(chunk.iter(), |mut b, rec| {
                 b.push_bind(&rec.nix_store_path)
                     .push_bind(&rec.revision.commit_hash)
                     .push_bind(&rec.revision.branch)
-                    .push_bind(rec.linked_at);
+                    .push_bind(rec.linked_at)
+                    .push_bind(&rec.hostname);
             });
             query_builder
                 .push(" ON CONFLICT (store_path, commit_hash, branch, linked_at) DO NOTHING");
@@ -30,4 +40,168 @@ impl NixGitLinkRepository {
         }
         Ok(i)
     }
+
+    /// Links of every commit whose hash starts with `commit_prefix`.
+    pub async fn find_by_commit(
+        pool: &Pool<Postgres>,
+        commit_prefix: &str,
+    ) -> Result<Vec<NixGitLinkModel>, RetError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT store_path, commit_hash, branch, linked_at, hostname FROM nix_git_link
+                WHERE starts_with(commit_hash, $1)
+                ORDER BY linked_at ASC, store_path
+            "#,
+            commit_prefix,
+        )
+        .map(|record| NixGitLinkModel {
+            nix_store_path: record.store_path,
+            revision: RevisionModel {
+                commit_hash: record.commit_hash,
+                branch: record.branch,
+            },
+            linked_at: record.linked_at,
+            hostname: record.hostname,
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// The store paths CI built from each of `commit_prefixes`, as
+    /// `(prefix, store path)` pairs.
+    pub async fn store_paths_by_commits(
+        pool: &Pool<Postgres>,
+        commit_prefixes: &[String],
+    ) -> Result<Vec<(String, String)>, RetError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT p.prefix AS "prefix!", n.store_path
+                FROM unnest($1::text[]) AS p(prefix)
+                JOIN nix_git_link n ON starts_with(n.commit_hash, p.prefix)
+            "#,
+            commit_prefixes,
+        )
+        .map(|record| (record.prefix, record.store_path))
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// The newest build of every host CI built a system for, by hostname.
+    /// `running` are the hosts and the store paths they run.
+    pub async fn latest_builds(
+        pool: &Pool<Postgres>,
+        running: &[(String, String)],
+        branch_precedence: &BranchPrecedence,
+    ) -> Result<HashMap<String, LatestBuild>, RetError> {
+        let (hostnames, store_paths): (Vec<String>, Vec<String>) = running.iter().cloned().unzip();
+        let rows = sqlx::query!(
+            r#"
+WITH running AS (
+  SELECT * FROM unnest($2::text[], $3::text[]) AS r(hostname, store_path)
+),
+latest AS (
+  SELECT DISTINCT ON (n.hostname) n.hostname, n.store_path, n.commit_hash, n.branch, n.linked_at
+  FROM nix_git_link n
+  WHERE n.hostname IS NOT NULL
+  ORDER BY n.hostname, (SELECT MIN(p.rank) FROM unnest($1::text[]) WITH ORDINALITY AS p(pattern, rank) WHERE n.branch LIKE p.pattern) ASC NULLS LAST, n.linked_at DESC
+)
+SELECT l.hostname AS "hostname!", l.store_path, l.commit_hash, l.branch, l.linked_at,
+       rl.linked_at AS "running_linked_at?", behind.commits AS "commits_behind?"
+FROM latest l
+LEFT JOIN running r ON r.hostname = l.hostname
+LEFT JOIN LATERAL (
+  SELECT MIN(n.linked_at) AS linked_at FROM nix_git_link n
+  WHERE n.store_path = r.store_path AND n.branch = l.branch
+) rl ON TRUE
+LEFT JOIN LATERAL (
+  SELECT COUNT(DISTINCT n.commit_hash) AS commits FROM nix_git_link n
+  WHERE n.hostname = l.hostname AND n.branch = l.branch AND n.linked_at > rl.linked_at
+) behind ON rl.linked_at IS NOT NULL;
+            "#,
+            branch_precedence.like_patterns(),
+            &hostnames,
+            &store_paths,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|record| {
+                let build = LatestBuild {
+                    store_path: record.store_path,
+                    revision: RevisionModel {
+                        commit_hash: record.commit_hash,
+                        branch: record.branch,
+                    },
+                    linked_at: record.linked_at,
+                    running_linked_at: record.running_linked_at,
+                    commits_behind: record.commits_behind,
+                };
+                (record.hostname, build)
+            })
+            .collect())
+    }
+
+    /// When CI first linked any of `store_paths` to a commit.
+    pub async fn first_linked_at(
+        pool: &Pool<Postgres>,
+        store_paths: &[String],
+    ) -> Result<Option<DateTime<Utc>>, RetError> {
+        let linked_at = sqlx::query_scalar!(
+            r#"
+            SELECT MIN(linked_at) FROM nix_git_link
+                WHERE store_path = ANY($1)
+            "#,
+            store_paths,
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(linked_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Pool, Postgres};
+
+    use super::NixGitLinkRepository;
+    use crate::server::repository::fixtures::link;
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_store_paths_by_commits(pool: Pool<Postgres>) {
+        link(
+            &pool,
+            &[
+                (0, "/nix/store/a", "abc123"),
+                (1, "/nix/store/b", "abc123"),
+                (2, "/nix/store/c", "abd456"),
+                (3, "/nix/store/d", "fff000"),
+            ],
+        )
+        .await;
+        let mut pairs = NixGitLinkRepository::store_paths_by_commits(
+            &pool,
+            &["abc".to_string(), "ab".to_string(), "000".to_string()],
+        )
+        .await
+        .unwrap();
+        pairs.sort();
+        let pairs: Vec<(&str, &str)> = pairs
+            .iter()
+            .map(|(prefix, store_path)| (prefix.as_str(), store_path.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("ab", "/nix/store/a"),
+                ("ab", "/nix/store/b"),
+                ("ab", "/nix/store/c"),
+                ("abc", "/nix/store/a"),
+                ("abc", "/nix/store/b"),
+            ]
+        );
+    }
 }
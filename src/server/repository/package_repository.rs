@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use sqlx::{Pool, Postgres, QueryBuilder};
+
+use crate::{server::RetError, shared::model::package::PackageModel};
+
+#[derive(Debug, Clone)]
+pub struct PackageRepository;
+
+impl PackageRepository {
+    /// Replaces the manifest of a store path.
+    pub async fn replace(
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        store_path: &str,
+        packages: &[PackageModel],
+    ) -> Result<u64, RetError> {
+        sqlx::query!("DELETE FROM package WHERE store_path = $1", store_path)
+            .execute(&mut **tx)
+            .await?;
+        const CHUNK_SIZE: usize = 1000;
+        let mut i = 0;
+        for chunk in packages.chunks(CHUNK_SIZE) {
+            let mut query_builder: QueryBuilder<Postgres> =
+                QueryBuilder::new("INSERT INTO package(store_path, name, version) ");
+            query_builder.push_values(chunk.iter(), |mut b, rec| {
+                b.push_bind(store_path)
+                    .push_bind(&rec.name)
+                    .push_bind(&rec.version);
+            });
+            query_builder.push(" ON CONFLICT (store_path, name, version) DO NOTHING");
+            let res = query_builder.build().execute(&mut **tx).await?;
+            i += res.rows_affected();
+        }
+        Ok(i)
+    }
+
+    /// The store paths that have a manifest.
+    pub async fn with_manifest(
+        pool: &Pool<Postgres>,
+        store_paths: &[String],
+    ) -> Result<Vec<String>, RetError> {
+        let rows = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT store_path FROM package
+                WHERE store_path = ANY($1)
+            "#,
+            store_paths,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// The manifest of every store path that has one, by store path.
+    pub async fn get_by_store_paths(
+        pool: &Pool<Postgres>,
+        store_paths: &[String],
+    ) -> Result<HashMap<String, Vec<PackageModel>>, RetError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT store_path, name, version FROM package
+                WHERE store_path = ANY($1)
+                ORDER BY name, version
+            "#,
+            store_paths,
+        )
+        .fetch_all(pool)
+        .await?;
+        let mut packages: HashMap<String, Vec<PackageModel>> = HashMap::new();
+        for row in rows {
+            packages
+                .entry(row.store_path)
+                .or_default()
+                .push(PackageModel {
+                    name: row.name,
+                    version: row.version,
+                });
+        }
+        Ok(packages)
+    }
+}
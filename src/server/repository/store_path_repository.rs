@@ -1,6 +1,14 @@
-use sqlx::{Postgres, QueryBuilder};
+use sqlx::{Pool, Postgres, QueryBuilder};
 
-use crate::server::RetError;
+use crate::{
+    server::RetError,
+    shared::model::{
+        activation::{DEPLOY_ACTIVATION_TYPES, RUNNING_ACTIVATION_TYPES},
+        nix_git_link::NixGitLinkModel,
+        revision::RevisionModel,
+        store_path::StorePathHost,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct StorePathRepository;
@@ -26,4 +34,91 @@ impl StorePathRepository {
         }
         Ok(i)
     }
+
+    /// Store paths equal to `store_path` or starting with `prefix`.
+    pub(crate) async fn find_store_paths(
+        pool: &Pool<Postgres>,
+        store_path: &str,
+        prefix: &str,
+    ) -> Result<Vec<String>, RetError> {
+        let rows = sqlx::query_scalar!(
+            r#"
+            SELECT store_path FROM nix_store_path
+                WHERE store_path = $1 OR starts_with(store_path, $2)
+                ORDER BY store_path
+            "#,
+            store_path,
+            prefix,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    pub(crate) async fn get_links(
+        pool: &Pool<Postgres>,
+        store_path: &str,
+    ) -> Result<Vec<NixGitLinkModel>, RetError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT store_path, commit_hash, branch, linked_at, hostname FROM nix_git_link
+                WHERE store_path = $1
+                ORDER BY linked_at ASC, branch
+            "#,
+            store_path,
+        )
+        .map(|record| NixGitLinkModel {
+            nix_store_path: record.store_path,
+            revision: RevisionModel {
+                commit_hash: record.commit_hash,
+                branch: record.branch,
+            },
+            linked_at: record.linked_at,
+            hostname: record.hostname,
+        })
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Every host that deployed `store_path`, and whether it is running it now.
+    pub(crate) async fn get_hosts(
+        pool: &Pool<Postgres>,
+        store_path: &str,
+    ) -> Result<Vec<StorePathHost>, RetError> {
+        let deploy_types: Vec<String> = DEPLOY_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let running_types: Vec<String> = RUNNING_ACTIVATION_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let rows = sqlx::query_as!(
+            StorePathHost,
+            r#"
+WITH running AS (
+SELECT
+DISTINCT ON (ac.hostname)
+ac.hostname, ac.store_path
+FROM activation ac
+     WHERE ac.activation_type = ANY($3)
+     ORDER BY ac.hostname, ac.activated_at DESC
+)
+SELECT a.hostname, MIN(a.activated_at) AS "first_activated_at!", MAX(a.activated_at) AS "last_activated_at!",
+       COUNT(*) AS "activations!", COALESCE(BOOL_OR(r.store_path = a.store_path), FALSE) AS "running!"
+FROM activation a
+LEFT JOIN running r ON r.hostname = a.hostname
+WHERE a.store_path = $1 AND a.activation_type = ANY($2)
+GROUP BY a.hostname
+ORDER BY a.hostname;
+            "#,
+            store_path,
+            &deploy_types,
+            &running_types,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
 }
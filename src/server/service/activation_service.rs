@@ -1,51 +1,94 @@
 use std::collections::BTreeMap;
 
-use chrono::NaiveDate;
-use chrono_tz::Tz;
+use chrono::{DateTime, NaiveDate, Utc};
 use sqlx::{Pool, Postgres};
 
 use crate::{
     server::{
-        TIME_ZONE_ENV_NAME,
+        branch_precedence::BranchPrecedence,
+        configured_time_zone,
         custom_error::RetError,
+        events::HOST_ACTIVATIONS_CHANNEL,
         repository::{
             activation_repository::ActivationRepository, store_path_repository::StorePathRepository,
         },
     },
-    shared::model::activation::{ActivationWithRevision, NewActivation},
+    shared::model::{
+        activation::{Activation, HistoryEntry, NewActivation},
+        activity::{ActivityEntry, ActivityFilter},
+    },
 };
 
+#[derive(Debug, Clone)]
+pub struct InsertedActivations {
+    pub inserted: u64,
+    /// The inserted deploys of store paths with unknown provenance that are
+    /// newer than the server and than what the host had activated before.
+    pub unknown_provenance: Vec<Activation>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ActivationLogService {
     pool: Pool<Postgres>,
+    branch_precedence: BranchPrecedence,
+    started_at: DateTime<Utc>,
 }
 
 impl ActivationLogService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool<Postgres>, branch_precedence: BranchPrecedence) -> Self {
+        Self {
+            pool,
+            branch_precedence,
+            started_at: Utc::now(),
+        }
     }
 
     pub async fn host_with_logs_by_hostname(
         &self,
         hostname: &str,
-    ) -> Result<BTreeMap<NaiveDate, Vec<ActivationWithRevision>>, RetError> {
-        let logs = ActivationRepository::get_logs_by_hostname(&self.pool, hostname).await?;
+    ) -> Result<BTreeMap<NaiveDate, Vec<HistoryEntry>>, RetError> {
+        let logs = ActivationRepository::get_logs_by_hostname(
+            &self.pool,
+            hostname,
+            &self.branch_precedence,
+        )
+        .await?;
 
-        let tz_str = std::env::var(TIME_ZONE_ENV_NAME).unwrap_or_else(|_| "UTC".to_string());
-        let tz: Tz = tz_str.parse().unwrap_or(chrono_tz::UTC);
+        let tz = configured_time_zone();
 
-        let mut map: BTreeMap<NaiveDate, Vec<ActivationWithRevision>> = BTreeMap::new();
+        let mut map: BTreeMap<NaiveDate, Vec<HistoryEntry>> = BTreeMap::new();
         for log in logs {
-            let date = log.activated_at.with_timezone(&tz).date_naive();
+            let date = log.activation.activated_at.with_timezone(&tz).date_naive();
             map.entry(date).or_default().push(log);
         }
         Ok(map)
     }
 
+    /// The activations of a host, newest first. `rollback` keeps only rollbacks or only the rest.
+    pub async fn get_history(
+        &self,
+        hostname: &str,
+        rollback: Option<bool>,
+    ) -> Result<Vec<HistoryEntry>, RetError> {
+        let logs = ActivationRepository::get_logs_by_hostname(
+            &self.pool,
+            hostname,
+            &self.branch_precedence,
+        )
+        .await?;
+        Ok(logs
+            .into_iter()
+            .filter(|log| rollback.is_none_or(|rollback| log.is_rollback == rollback))
+            .collect())
+    }
+
+    /// Inserts the activations it has not seen and returns those of them
+    /// deploying a store path with unknown provenance. The history a host
+    /// reports on its first ingest is not returned.
     pub(crate) async fn bulk_insert_log_records(
         &self,
         new_activations: &[NewActivation],
-    ) -> Result<u64, RetError> {
+    ) -> Result<InsertedActivations, RetError> {
         let mut tx = self.pool.begin().await?;
 
         let store_paths: Vec<&str> = new_activations
@@ -54,8 +97,156 @@ impl ActivationLogService {
             .collect();
         StorePathRepository::bulk_insert_store_paths(&mut tx, &store_paths).await?;
 
-        let i = ActivationRepository::insert_many(&mut tx, new_activations).await?;
+        let mut hostnames: Vec<String> = new_activations
+            .iter()
+            .map(|el| el.core.hostname.clone())
+            .collect();
+        hostnames.sort();
+        hostnames.dedup();
+        let previous_latest =
+            ActivationRepository::latest_activated_at(&mut tx, &hostnames).await?;
+
+        let inserted_ids = ActivationRepository::insert_many(&mut tx, new_activations).await?;
+        if !inserted_ids.is_empty() {
+            ActivationRepository::notify_hosts(&mut tx, HOST_ACTIVATIONS_CHANNEL, &inserted_ids)
+                .await?;
+        }
         tx.commit().await?;
-        Ok(i)
+        let unknown_provenance = if inserted_ids.is_empty() {
+            Vec::new()
+        } else {
+            ActivationRepository::get_unknown_provenance(&self.pool, Some(&inserted_ids), None, 0)
+                .await?
+                .into_iter()
+                .filter(|activation| {
+                    let seen_until = previous_latest
+                        .get(&activation.core.hostname)
+                        .map_or(self.started_at, |latest| self.started_at.max(*latest));
+                    activation.core.activated_at > seen_until
+                })
+                .collect()
+        };
+        Ok(InsertedActivations {
+            inserted: inserted_ids.len() as u64,
+            unknown_provenance,
+        })
+    }
+
+    /// A page of the activity feed and whether there are more after it.
+    pub async fn get_activity(
+        &self,
+        filter: &ActivityFilter,
+        page: u32,
+        per_page: u32,
+    ) -> Result<(Vec<ActivityEntry>, bool), RetError> {
+        let per_page = i64::from(per_page);
+        let offset = i64::from(page.saturating_sub(1)) * per_page;
+        let rows = ActivationRepository::get_activity(
+            &self.pool,
+            filter,
+            per_page + 1,
+            offset,
+            &self.branch_precedence,
+        )
+        .await?;
+        let mut entries = ActivityEntry::group_rows(rows);
+        let has_more = entries.len() as i64 > per_page;
+        entries.truncate(per_page as usize);
+        Ok((entries, has_more))
+    }
+
+    /// The latest `limit` activations of a host, a metadata group or every host.
+    pub async fn get_recent(
+        &self,
+        hostname: Option<&str>,
+        group: Option<(&str, &str)>,
+        limit: i64,
+    ) -> Result<Vec<Activation>, RetError> {
+        let rows = ActivationRepository::get_recent(
+            &self.pool,
+            hostname,
+            group,
+            limit,
+            &self.branch_precedence,
+        )
+        .await?;
+        Ok(rows.into_iter().map(Activation::from).collect())
+    }
+
+    /// A page of the deploys with unknown provenance and whether there are more after it.
+    pub async fn get_unknown_provenance(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Result<(Vec<Activation>, bool), RetError> {
+        let per_page = i64::from(per_page);
+        let offset = i64::from(page.saturating_sub(1)) * per_page;
+        let mut activations = ActivationRepository::get_unknown_provenance(
+            &self.pool,
+            None,
+            Some(per_page + 1),
+            offset,
+        )
+        .await?;
+        let has_more = activations.len() as i64 > per_page;
+        activations.truncate(per_page as usize);
+        Ok((activations, has_more))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::repository::fixtures::{activate, at, insert_hosts, new_activations};
+
+    fn unknown_paths(inserted: InsertedActivations) -> Vec<String> {
+        inserted
+            .unknown_provenance
+            .into_iter()
+            .map(|activation| activation.core.store_path)
+            .collect()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_unknown_provenance_skips_history(pool: Pool<Postgres>) {
+        insert_hosts(&pool, &["a", "b"]).await;
+        activate(&pool, "a", &[(10, "/nix/store/a", "switch")]).await;
+        let service = ActivationLogService {
+            started_at: at(5),
+            ..ActivationLogService::new(pool, BranchPrecedence::new(&[]))
+        };
+        let insert = |hostname, activations| {
+            let service = service.clone();
+            let activations = new_activations(hostname, activations);
+            async move { unknown_paths(service.bulk_insert_log_records(&activations).await.unwrap()) }
+        };
+        // older than the latest activation host a had
+        assert_eq!(
+            insert(
+                "a",
+                &[
+                    (8, "/nix/store/x", "switch"),
+                    (12, "/nix/store/y", "switch")
+                ]
+            )
+            .await,
+            vec!["/nix/store/y"]
+        );
+        // host b reports for the first time, only what happened since the server started
+        assert_eq!(
+            insert(
+                "b",
+                &[(1, "/nix/store/x", "switch"), (6, "/nix/store/z", "switch")]
+            )
+            .await,
+            vec!["/nix/store/z"]
+        );
+        // nothing new
+        assert!(
+            insert("b", &[(6, "/nix/store/z", "switch")])
+                .await
+                .is_empty()
+        );
     }
 }
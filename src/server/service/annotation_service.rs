@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::{
+        custom_error::RetError,
+        repository::{
+            annotation_repository::AnnotationRepository, host_repository::HostRepository,
+        },
+    },
+    shared::{
+        dto::annotation::{AnnotationContentDto, AnnotationDto, NewAnnotationDto},
+        model::annotation::AnnotationContent,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct AnnotationService {
+    pool: Pool<Postgres>,
+}
+
+fn validate(content: &AnnotationContent) -> Result<(), RetError> {
+    if content.author.trim().is_empty() {
+        return Err(RetError::BadRequest("author must not be empty".to_string()));
+    }
+    if content.body.trim().is_empty() {
+        return Err(RetError::BadRequest("body must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+impl AnnotationService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        hostname: &str,
+        NewAnnotationDto {
+            author,
+            body,
+            activation_id,
+            expires_at,
+        }: NewAnnotationDto,
+    ) -> Result<AnnotationDto, RetError> {
+        let content = AnnotationContent {
+            author,
+            body,
+            expires_at,
+        };
+        validate(&content)?;
+        let annotation =
+            AnnotationRepository::insert(&self.pool, hostname, activation_id, &content)
+                .await?
+                .ok_or(RetError::NotFound)?;
+        Ok((annotation, Utc::now()).into())
+    }
+
+    pub async fn update(
+        &self,
+        annotation_id: i64,
+        content: AnnotationContentDto,
+    ) -> Result<AnnotationDto, RetError> {
+        let content = AnnotationContent::from(content);
+        validate(&content)?;
+        let annotation = AnnotationRepository::update(&self.pool, annotation_id, &content)
+            .await?
+            .ok_or(RetError::NotFound)?;
+        Ok((annotation, Utc::now()).into())
+    }
+
+    pub async fn expire(&self, annotation_id: i64) -> Result<AnnotationDto, RetError> {
+        let annotation = AnnotationRepository::expire(&self.pool, annotation_id)
+            .await?
+            .ok_or(RetError::NotFound)?;
+        Ok((annotation, Utc::now()).into())
+    }
+
+    /// Every note on the host, expired ones too. NotFound if there is no such host.
+    pub async fn get_by_hostname(&self, hostname: &str) -> Result<Vec<AnnotationDto>, RetError> {
+        HostRepository::get_host_from_hostname(&self.pool, hostname.to_string())
+            .await?
+            .ok_or(RetError::NotFound)?;
+        let now = Utc::now();
+        let annotations = AnnotationRepository::get_by_hostname(&self.pool, hostname).await?;
+        Ok(annotations
+            .into_iter()
+            .map(|annotation| (annotation, now).into())
+            .collect())
+    }
+
+    /// Notes on hosts that have not expired, by hostname.
+    pub async fn get_active_by_host(
+        &self,
+    ) -> Result<HashMap<String, Vec<AnnotationDto>>, RetError> {
+        let now = Utc::now();
+        let mut by_host: HashMap<String, Vec<AnnotationDto>> = HashMap::new();
+        for annotation in AnnotationRepository::get_active_host_notes(&self.pool).await? {
+            by_host
+                .entry(annotation.hostname.clone())
+                .or_default()
+                .push((annotation, now).into());
+        }
+        Ok(by_host)
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::{
+        custom_error::RetError,
+        repository::{
+            desired_revision_repository::DesiredRevisionRepository,
+            nix_git_link_repository::NixGitLinkRepository,
+        },
+    },
+    shared::{
+        dto::desired_revision::{DesiredRevisionDto, SetDesiredRevisionDto},
+        model::{
+            desired_revision::{DesiredRevisionModel, DesiredTarget, HostCompliance},
+            host::HostWithState,
+        },
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct DesiredRevisionService {
+    pool: Pool<Postgres>,
+}
+
+impl DesiredRevisionService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn set(
+        &self,
+        grouping_key: String,
+        group_value: String,
+        SetDesiredRevisionDto {
+            commit_hash,
+            store_path,
+            deadline,
+            author,
+        }: SetDesiredRevisionDto,
+    ) -> Result<DesiredRevisionDto, RetError> {
+        let target = match (commit_hash, store_path) {
+            (Some(commit_hash), None) if !commit_hash.trim().is_empty() => {
+                DesiredTarget::Commit(commit_hash.trim().to_string())
+            }
+            (None, Some(store_path)) if !store_path.trim().is_empty() => {
+                let store_path = store_path.trim();
+                DesiredTarget::StorePath(if store_path.starts_with("/nix/store/") {
+                    store_path.to_string()
+                } else {
+                    format!("/nix/store/{store_path}")
+                })
+            }
+            _ => {
+                return Err(RetError::BadRequest(
+                    "set exactly one of commit_hash and store_path".to_string(),
+                ));
+            }
+        };
+        if author.trim().is_empty() {
+            return Err(RetError::BadRequest("author must not be empty".to_string()));
+        }
+        let desired = DesiredRevisionModel {
+            grouping_key,
+            group_value,
+            target,
+            deadline,
+            author,
+            set_at: Utc::now(),
+        };
+        let desired = DesiredRevisionRepository::upsert(&self.pool, &desired).await?;
+        Ok(desired.into())
+    }
+
+    pub async fn clear(&self, grouping_key: &str, group_value: &str) -> Result<(), RetError> {
+        if DesiredRevisionRepository::delete(&self.pool, grouping_key, group_value).await? {
+            Ok(())
+        } else {
+            Err(RetError::NotFound)
+        }
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<DesiredRevisionDto>, RetError> {
+        let targets = DesiredRevisionRepository::get_all(&self.pool).await?;
+        Ok(targets.into_iter().map(Into::into).collect())
+    }
+
+    /// Compliance of the hosts that are in a group with a target.
+    pub async fn compliance(
+        &self,
+        hosts: &[HostWithState],
+    ) -> Result<Vec<HostCompliance>, RetError> {
+        let targets = DesiredRevisionRepository::get_all(&self.pool).await?;
+        let commits: Vec<String> = targets
+            .iter()
+            .filter_map(|desired| match &desired.target {
+                DesiredTarget::Commit(commit) => Some(commit.clone()),
+                DesiredTarget::StorePath(_) => None,
+            })
+            .collect();
+        let mut linked_store_paths: HashMap<String, HashSet<String>> = HashMap::new();
+        if !commits.is_empty() {
+            for (commit, store_path) in
+                NixGitLinkRepository::store_paths_by_commits(&self.pool, &commits).await?
+            {
+                linked_store_paths
+                    .entry(commit)
+                    .or_default()
+                    .insert(store_path);
+            }
+        }
+        let now = Utc::now();
+        Ok(hosts
+            .iter()
+            .filter_map(|host| HostCompliance::new(host, &targets, &linked_store_paths, now))
+            .collect())
+    }
+}
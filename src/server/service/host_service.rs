@@ -1,31 +1,124 @@
+use chrono::Utc;
 use sqlx::{Pool, Postgres};
 
 use crate::{
-    server::custom_error::RetError,
-    server::repository::host_repository::HostRepository,
-    shared::model::host::{HostModel, HostWithLatestLog},
+    server::repository::{
+        activation_repository::ActivationRepository, flake_input_repository::FlakeInputRepository,
+        host_repository::HostRepository, nix_git_link_repository::NixGitLinkRepository,
+    },
+    server::{
+        branch_precedence::BranchPrecedence,
+        custom_error::RetError,
+        host_query::{HostQuery, HostSort},
+    },
+    shared::model::host::{HostMetricsRow, HostModel, HostWithState},
 };
 
+/// Deploys per host the frontpage counts rollbacks among, older ones are not
+/// looked at so the count stays cheap on a long history.
+const ROLLBACK_WINDOW: i64 = 100;
+
 #[derive(Debug, Clone)]
 pub struct HostService {
     pool: Pool<Postgres>,
+    branch_precedence: BranchPrecedence,
 }
 
 impl HostService {
-    pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+    pub fn new(pool: Pool<Postgres>, branch_precedence: BranchPrecedence) -> Self {
+        Self {
+            pool,
+            branch_precedence,
+        }
+    }
+
+    pub async fn get_all_with_state(&self) -> Result<Vec<HostWithState>, RetError> {
+        self.load_with_state(None).await
+    }
+
+    pub async fn get_with_state(&self, hostname: &str) -> Result<Option<HostWithState>, RetError> {
+        let hosts = self.load_with_state(Some(hostname)).await?;
+        Ok(hosts.into_iter().next())
     }
 
-    pub async fn get_all_with_latest_log(&self) -> Result<Vec<HostWithLatestLog>, RetError> {
-        let hosts = HostRepository::get_all_hosts_with_latest_activation(&self.pool).await?;
+    /// Every host with its state, or only `hostname` when given.
+    async fn load_with_state(
+        &self,
+        hostname: Option<&str>,
+    ) -> Result<Vec<HostWithState>, RetError> {
+        let mut hosts =
+            HostRepository::get_hosts_with_state(&self.pool, hostname, &self.branch_precedence)
+                .await?;
+        let rollbacks = ActivationRepository::count_rollbacks_per_host(
+            &self.pool,
+            hostname,
+            &self.branch_precedence,
+            ROLLBACK_WINDOW,
+        )
+        .await?;
+        let running: Vec<(String, String)> = hosts
+            .iter()
+            .filter_map(|host| {
+                let running = host.running.as_ref()?;
+                Some((host.host.hostname.clone(), running.core.store_path.clone()))
+            })
+            .collect();
+        let mut latest_builds =
+            NixGitLinkRepository::latest_builds(&self.pool, &running, &self.branch_precedence)
+                .await?;
+        let running_store_paths: Vec<String> = running
+            .iter()
+            .map(|(_, store_path)| store_path.clone())
+            .collect();
+        let inputs =
+            FlakeInputRepository::get_by_store_paths(&self.pool, &running_store_paths).await?;
+        for host in &mut hosts {
+            host.rollbacks = rollbacks.get(&host.host.hostname).copied().unwrap_or(0);
+            host.latest_build = latest_builds.remove(&host.host.hostname);
+            if let Some(running) = &host.running {
+                host.running_inputs = inputs
+                    .get(&running.core.store_path)
+                    .cloned()
+                    .unwrap_or_default();
+            }
+        }
         Ok(hosts)
     }
 
-    pub async fn create_many(&self, hosts: &[HostModel]) -> Result<u64, sqlx::Error> {
-        let hosts = HostRepository::bulk_insert_hosts(&self.pool, hosts).await?;
+    /// The hosts matching `query`, in `sort` order.
+    pub async fn get_matching(
+        &self,
+        query: &HostQuery,
+        sort: &HostSort,
+    ) -> Result<Vec<HostWithState>, RetError> {
+        let now = Utc::now();
+        let mut hosts = self.get_all_with_state().await?;
+        hosts.retain(|host| query.matches(host, now));
+        sort.sort(&mut hosts);
         Ok(hosts)
     }
 
+    /// Upserts the hosts, hosts left out are not touched.
+    pub async fn create_many(&self, hosts: &[HostModel]) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let inserted = HostRepository::bulk_insert_hosts(&mut tx, hosts).await?;
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    pub async fn set_retired(&self, hostname: &str, retired: bool) -> Result<(), RetError> {
+        if !HostRepository::set_retired(&self.pool, hostname, retired).await? {
+            return Err(RetError::NotFound);
+        }
+        tracing::info!(hostname, retired, "changed retirement of host");
+        Ok(())
+    }
+
+    pub async fn get_host_metrics(&self) -> Result<Vec<HostMetricsRow>, RetError> {
+        let rows = HostRepository::get_host_metrics(&self.pool, &self.branch_precedence).await?;
+        Ok(rows)
+    }
+
     pub async fn get_host_from_hostname(
         &self,
         hostname: String,
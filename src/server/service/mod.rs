@@ -1,3 +1,8 @@
 pub(crate) mod activation_service;
+pub(crate) mod annotation_service;
+pub(crate) mod desired_revision_service;
 pub(crate) mod host_service;
 pub(crate) mod nix_git_link_service;
+pub(crate) mod package_service;
+pub(crate) mod rollout_service;
+pub(crate) mod store_path_service;
@@ -4,11 +4,12 @@ use crate::{
     server::{
         RetError,
         repository::{
+            flake_input_repository::FlakeInputRepository,
             nix_git_link_repository::NixGitLinkRepository,
             store_path_repository::StorePathRepository,
         },
     },
-    shared::dto::nix_git_link::NixGitLinkDto,
+    shared::{dto::nix_git_link::NixGitLinkDto, model::nix_git_link::FlakeInputModel},
 };
 #[derive(Debug, Clone)]
 pub struct NixGitLinkService {
@@ -26,6 +27,11 @@ impl NixGitLinkService {
             .map(|el| el.nix_store_path.as_str())
             .collect();
         StorePathRepository::bulk_insert_store_paths(&mut tx, &store_paths).await?;
+        let inputs: Vec<FlakeInputModel> = nix_git_links
+            .iter()
+            .flat_map(NixGitLinkDto::flake_inputs)
+            .collect();
+        FlakeInputRepository::create_many(&mut tx, &inputs).await?;
         let models = nix_git_links.into_iter().map(|dto| dto.into()).collect();
         let i = NixGitLinkRepository::create_many(&mut tx, models).await?;
         tx.commit().await?;
@@ -36,3 +42,42 @@ impl NixGitLinkService {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{server::repository::fixtures::at, shared::dto::revision::RevisionDto};
+    use std::collections::BTreeMap;
+
+    fn link(branch: &str, nixpkgs: &str) -> NixGitLinkDto {
+        NixGitLinkDto {
+            nix_store_path: "/nix/store/a".to_string(),
+            revision: RevisionDto {
+                commit_hash: "c1".to_string(),
+                branch: branch.to_string(),
+            },
+            deployed_at: at(0),
+            hostname: None,
+            inputs: BTreeMap::from([("nixpkgs".to_string(), nixpkgs.to_string())]),
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a postgres database in DATABASE_URL"]
+    async fn test_store_path_twice_in_one_batch(pool: Pool<Postgres>) {
+        let service = NixGitLinkService::new(pool.clone());
+        // one build linked to two branches
+        let inserted = service
+            .create_many(vec![link("master", "1a2b3c"), link("release", "4d5e6f")])
+            .await
+            .unwrap();
+        assert_eq!(inserted, 2);
+        let inputs = FlakeInputRepository::get_by_store_paths(&pool, &["/nix/store/a".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            inputs["/nix/store/a"],
+            BTreeMap::from([("nixpkgs".to_string(), "4d5e6f".to_string())])
+        );
+    }
+}
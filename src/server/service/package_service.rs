@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::{
+        RetError,
+        branch_precedence::BranchPrecedence,
+        repository::{
+            activation_repository::ActivationRepository, package_repository::PackageRepository,
+            store_path_repository::StorePathRepository,
+        },
+    },
+    shared::{
+        dto::package::{PackageDiffDto, PackageManifestDto},
+        model::{
+            activation::Activation,
+            package::{ActivationPackages, PackageModel},
+        },
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct PackageService {
+    pool: Pool<Postgres>,
+    branch_precedence: BranchPrecedence,
+}
+
+impl PackageService {
+    pub fn new(pool: Pool<Postgres>, branch_precedence: BranchPrecedence) -> Self {
+        Self {
+            pool,
+            branch_precedence,
+        }
+    }
+
+    /// Stores the manifest of a store path, replacing the one it had.
+    pub async fn upload(
+        &self,
+        PackageManifestDto {
+            store_path,
+            packages,
+        }: PackageManifestDto,
+    ) -> Result<u64, RetError> {
+        if store_path.trim().is_empty() {
+            return Err(RetError::BadRequest(
+                "store_path must not be empty".to_string(),
+            ));
+        }
+        if packages
+            .iter()
+            .any(|package| package.name.trim().is_empty())
+        {
+            return Err(RetError::BadRequest(
+                "package names must not be empty".to_string(),
+            ));
+        }
+        let packages: Vec<PackageModel> = packages.into_iter().map(Into::into).collect();
+        let mut tx = self.pool.begin().await?;
+        StorePathRepository::bulk_insert_store_paths(&mut tx, &[store_path.as_str()]).await?;
+        let i = PackageRepository::replace(&mut tx, &store_path, &packages).await?;
+        tx.commit().await?;
+        Ok(i)
+    }
+
+    /// The store paths CI uploaded a manifest for.
+    pub async fn with_manifest(&self, store_paths: &[String]) -> Result<HashSet<String>, RetError> {
+        let store_paths = PackageRepository::with_manifest(&self.pool, store_paths).await?;
+        Ok(store_paths.into_iter().collect())
+    }
+
+    /// The package changes from the store path of one activation to that of
+    /// another, None if either activation does not exist.
+    pub async fn diff(&self, from_id: i64, to_id: i64) -> Result<Option<PackageDiffDto>, RetError> {
+        let activations: Vec<Activation> = ActivationRepository::get_by_ids(
+            &self.pool,
+            &[from_id, to_id],
+            &self.branch_precedence,
+        )
+        .await?
+        .into_iter()
+        .map(Activation::from)
+        .collect();
+        let find = |id: i64| activations.iter().find(|activation| activation.id == id);
+        let (Some(from), Some(to)) = (find(from_id), find(to_id)) else {
+            return Ok(None);
+        };
+        let store_paths = [from.core.store_path.clone(), to.core.store_path.clone()];
+        let packages = PackageRepository::get_by_store_paths(&self.pool, &store_paths).await?;
+        let side = |activation: &Activation| ActivationPackages {
+            activation: activation.clone(),
+            packages: packages.get(&activation.core.store_path).cloned(),
+        };
+        Ok(Some((side(from), side(to)).into()))
+    }
+}
@@ -0,0 +1,108 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::{
+        RetError,
+        repository::{
+            host_repository::HostRepository, nix_git_link_repository::NixGitLinkRepository,
+        },
+        service::store_path_service::StorePathService,
+    },
+    shared::model::{nix_git_link::NixGitLinkModel, rollout::Rollout},
+};
+
+#[derive(Debug, Clone)]
+pub struct RolloutService {
+    pool: Pool<Postgres>,
+    store_path_service: StorePathService,
+}
+
+impl RolloutService {
+    pub fn new(pool: Pool<Postgres>, store_path_service: StorePathService) -> Self {
+        Self {
+            pool,
+            store_path_service,
+        }
+    }
+
+    /// Rollout of every store path CI linked to the commit `commit_key` is the
+    /// hash or an unambiguous prefix of.
+    pub async fn get_commit_rollout(
+        &self,
+        commit_key: &str,
+        grouping_key: Option<String>,
+    ) -> Result<Option<Rollout>, RetError> {
+        if commit_key.is_empty() {
+            return Ok(None);
+        }
+        let links = NixGitLinkRepository::find_by_commit(&self.pool, commit_key).await?;
+        let Some(commit_hash) = pick_commit(&links, commit_key) else {
+            return Ok(None);
+        };
+        let links: Vec<&NixGitLinkModel> = links
+            .iter()
+            .filter(|link| link.revision.commit_hash == commit_hash)
+            .collect();
+        let linked_at = links.iter().map(|link| link.linked_at).min();
+        let mut store_paths: Vec<String> = links
+            .iter()
+            .map(|link| link.nix_store_path.clone())
+            .collect();
+        store_paths.sort();
+        store_paths.dedup();
+
+        let hosts = HostRepository::get_rollout_hosts(&self.pool, &store_paths).await?;
+        Ok(Some(Rollout::new(
+            Some(commit_hash),
+            store_paths,
+            linked_at,
+            hosts,
+            grouping_key,
+        )))
+    }
+
+    /// Rollout of a set of store paths, given as keys like those of the store path page.
+    pub async fn get_store_paths_rollout(
+        &self,
+        store_path_keys: &[&str],
+        grouping_key: Option<String>,
+    ) -> Result<Option<Rollout>, RetError> {
+        let mut store_paths = Vec::new();
+        for key in store_path_keys {
+            match self.store_path_service.resolve(key).await? {
+                Some(store_path) => store_paths.push(store_path),
+                None => return Ok(None),
+            }
+        }
+        if store_paths.is_empty() {
+            return Ok(None);
+        }
+        store_paths.sort();
+        store_paths.dedup();
+
+        let linked_at = NixGitLinkRepository::first_linked_at(&self.pool, &store_paths).await?;
+        let hosts = HostRepository::get_rollout_hosts(&self.pool, &store_paths).await?;
+        Ok(Some(Rollout::new(
+            None,
+            store_paths,
+            linked_at,
+            hosts,
+            grouping_key,
+        )))
+    }
+}
+
+/// The full hash `commit_key` refers to, if it names exactly one commit.
+fn pick_commit(links: &[NixGitLinkModel], commit_key: &str) -> Option<String> {
+    let mut hashes: Vec<&str> = links
+        .iter()
+        .map(|link| link.revision.commit_hash.as_str())
+        .collect();
+    hashes.sort();
+    hashes.dedup();
+    match hashes.as_slice() {
+        _ if hashes.contains(&commit_key) => Some(commit_key.to_string()),
+        [only] => Some(only.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,108 @@
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    server::{
+        RetError, nix_name_fn,
+        repository::{
+            flake_input_repository::FlakeInputRepository,
+            store_path_repository::StorePathRepository,
+        },
+    },
+    shared::model::store_path::StorePathDetail,
+};
+
+const STORE_DIR: &str = "/nix/store/";
+
+#[derive(Debug, Clone)]
+pub struct StorePathService {
+    pool: Pool<Postgres>,
+}
+
+impl StorePathService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Looks up a store path by its full path, its name in the store, the
+    /// `nix_name` short form or an unambiguous hash prefix.
+    pub async fn get_detail(&self, key: &str) -> Result<Option<StorePathDetail>, RetError> {
+        let Some(store_path) = self.resolve(key).await? else {
+            return Ok(None);
+        };
+
+        let links = StorePathRepository::get_links(&self.pool, &store_path).await?;
+        let hosts = StorePathRepository::get_hosts(&self.pool, &store_path).await?;
+        let inputs =
+            FlakeInputRepository::get_by_store_paths(&self.pool, std::slice::from_ref(&store_path))
+                .await?
+                .remove(&store_path)
+                .unwrap_or_default();
+        Ok(Some(StorePathDetail {
+            store_path,
+            links,
+            inputs,
+            hosts,
+        }))
+    }
+
+    /// The full store path a key given to [`Self::get_detail`] refers to.
+    pub async fn resolve(&self, key: &str) -> Result<Option<String>, RetError> {
+        let name = key.strip_prefix(STORE_DIR).unwrap_or(key);
+        if name.is_empty() {
+            return Ok(None);
+        }
+        let full = format!("{STORE_DIR}{name}");
+        let prefix = format!("{STORE_DIR}{}", name.get(..10).unwrap_or(name));
+        let candidates = StorePathRepository::find_store_paths(&self.pool, &full, &prefix).await?;
+        Ok(pick_store_path(&candidates, name).map(str::to_string))
+    }
+}
+
+fn pick_store_path<'a>(candidates: &'a [String], name: &str) -> Option<&'a str> {
+    let full = format!("{STORE_DIR}{name}");
+    candidates
+        .iter()
+        .find(|c| **c == full)
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|c| nix_name_fn(c).is_some_and(|n| n == name))
+        })
+        .or(match candidates {
+            [only] => Some(only),
+            _ => None,
+        })
+        .map(String::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_store_path() {
+        let candidates = vec![
+            "/nix/store/4v0ykqdvvpgpw83ljfk32bzjl2bcblmk-nixos-system-hosts-p01-25.05pre-git"
+                .to_string(),
+            "/nix/store/4v0ykqdvvpzzzzzzzzzzzzzzzzzzzzzz-nixos-system-hosts-p02-25.05pre-git"
+                .to_string(),
+        ];
+        assert_eq!(
+            pick_store_path(
+                &candidates,
+                "4v0ykqdvvpgpw83ljfk32bzjl2bcblmk-nixos-system-hosts-p01-25.05pre-git"
+            ),
+            Some(candidates[0].as_str())
+        );
+        assert_eq!(
+            pick_store_path(&candidates, "4v0ykqdvvp-hosts-p02-25.05"),
+            Some(candidates[1].as_str())
+        );
+        // the hash prefix matches both
+        assert_eq!(pick_store_path(&candidates, "4v0ykqdvvp"), None);
+        assert_eq!(
+            pick_store_path(&candidates[..1], "4v0ykqdvvp"),
+            Some(candidates[0].as_str())
+        );
+    }
+}
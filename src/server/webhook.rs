@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crate::shared::dto::host::HostWithLogsDto;
+
+/// Posts the activations of a host that deployed a store path with unknown provenance.
+#[derive(Debug, Clone)]
+pub(crate) struct UnknownProvenanceWebhook {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl UnknownProvenanceWebhook {
+    pub(crate) fn new(url: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("could not build webhook client");
+        Self { client, url }
+    }
+
+    /// Sends in the background, a failing webhook is logged and does not fail ingest.
+    pub(crate) fn notify(&self, body: HostWithLogsDto) {
+        let client = self.client.clone();
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            let res = client
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|res| res.error_for_status());
+            if let Err(err) = res {
+                tracing::error!(error = %err, host = %body.hostname, "unknown provenance webhook failed");
+            }
+        });
+    }
+}
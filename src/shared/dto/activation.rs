@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::shared::{
     dto::revision::RevisionDto,
-    model::activation::{Activation, ActivationCore, NewActivation},
+    model::activation::{Activation, ActivationCore, HistoryEntry, NewActivation},
 };
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,3 +37,26 @@ impl From<NewActivation> for ActivationDto {
         Self::from(l.core)
     }
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryEntryDto {
+    pub activation_id: i64,
+    #[serde(flatten)]
+    pub activation: ActivationDto,
+    pub is_rollback: bool,
+}
+
+impl From<HistoryEntry> for HistoryEntryDto {
+    fn from(
+        HistoryEntry {
+            activation,
+            is_rollback,
+        }: HistoryEntry,
+    ) -> Self {
+        Self {
+            activation_id: activation.activation_id,
+            activation: Activation::from(activation).into(),
+            is_rollback,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::{
+    dto::revision::RevisionDto,
+    model::activity::{ActivityEntry, ActivityRow},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActivityActivationDto {
+    pub activation_id: i64,
+    pub hostname: String,
+    pub activated_at: DateTime<Utc>,
+    pub username: String,
+    pub activation_type: String,
+}
+
+impl From<ActivityRow> for ActivityActivationDto {
+    fn from(row: ActivityRow) -> Self {
+        Self {
+            activation_id: row.activation_id,
+            hostname: row.hostname,
+            activated_at: row.activated_at,
+            username: row.username,
+            activation_type: row.activation_type,
+        }
+    }
+}
+
+/// Consecutive activations of one store path, possibly across many hosts.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActivityEntryDto {
+    pub store_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<RevisionDto>,
+    pub first_activated_at: DateTime<Utc>,
+    pub last_activated_at: DateTime<Utc>,
+    /// Newest first.
+    pub activations: Vec<ActivityActivationDto>,
+}
+
+impl From<ActivityEntry> for ActivityEntryDto {
+    fn from(
+        ActivityEntry {
+            store_path,
+            commit_hash,
+            branch,
+            activations,
+        }: ActivityEntry,
+    ) -> Self {
+        let activations: Vec<ActivityActivationDto> =
+            activations.into_iter().map(Into::into).collect();
+        Self {
+            store_path,
+            revision: commit_hash
+                .zip(branch)
+                .map(|(commit_hash, branch)| RevisionDto {
+                    commit_hash,
+                    branch,
+                }),
+            first_activated_at: activations
+                .last()
+                .map(|a| a.activated_at)
+                .unwrap_or_default(),
+            last_activated_at: activations
+                .first()
+                .map(|a| a.activated_at)
+                .unwrap_or_default(),
+            activations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ActivityPageDto {
+    pub entries: Vec<ActivityEntryDto>,
+    pub page: u32,
+    pub per_page: u32,
+    pub has_more: bool,
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::model::annotation::{AnnotationContent, AnnotationModel};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnnotationDto {
+    pub annotation_id: i64,
+    pub hostname: String,
+    pub activation_id: Option<i64>,
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Not expired yet.
+    pub active: bool,
+}
+
+impl From<(AnnotationModel, DateTime<Utc>)> for AnnotationDto {
+    fn from((model, now): (AnnotationModel, DateTime<Utc>)) -> Self {
+        let active = model.is_active(now);
+        let AnnotationModel {
+            annotation_id,
+            hostname,
+            activation_id,
+            author,
+            body,
+            created_at,
+            updated_at,
+            expires_at,
+        } = model;
+        Self {
+            annotation_id,
+            hostname,
+            activation_id,
+            author,
+            body,
+            created_at,
+            updated_at,
+            expires_at,
+            active,
+        }
+    }
+}
+
+/// Body of creating a note, `activation_id` pins it to one activation of the host.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NewAnnotationDto {
+    pub author: String,
+    pub body: String,
+    #[serde(default)]
+    pub activation_id: Option<i64>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Body of editing a note, replaces its author, text and expiry.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AnnotationContentDto {
+    pub author: String,
+    pub body: String,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<AnnotationContentDto> for AnnotationContent {
+    fn from(
+        AnnotationContentDto {
+            author,
+            body,
+            expires_at,
+        }: AnnotationContentDto,
+    ) -> Self {
+        Self {
+            author,
+            body,
+            expires_at,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::model::desired_revision::{
+    ComplianceStatus, DesiredRevisionModel, DesiredTarget, HostCompliance,
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DesiredRevisionDto {
+    pub grouping_key: String,
+    pub group_value: String,
+    /// Exactly one of `commit_hash` and `store_path` is set.
+    pub commit_hash: Option<String>,
+    pub store_path: Option<String>,
+    pub deadline: Option<DateTime<Utc>>,
+    pub author: String,
+    pub set_at: DateTime<Utc>,
+}
+
+impl From<DesiredRevisionModel> for DesiredRevisionDto {
+    fn from(
+        DesiredRevisionModel {
+            grouping_key,
+            group_value,
+            target,
+            deadline,
+            author,
+            set_at,
+        }: DesiredRevisionModel,
+    ) -> Self {
+        let (commit_hash, store_path) = match target {
+            DesiredTarget::Commit(commit_hash) => (Some(commit_hash), None),
+            DesiredTarget::StorePath(store_path) => (None, Some(store_path)),
+        };
+        Self {
+            grouping_key,
+            group_value,
+            commit_hash,
+            store_path,
+            deadline,
+            author,
+            set_at,
+        }
+    }
+}
+
+/// Body of declaring what a group should run, with either `commit_hash` or `store_path`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SetDesiredRevisionDto {
+    #[serde(default)]
+    pub commit_hash: Option<String>,
+    #[serde(default)]
+    pub store_path: Option<String>,
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    pub author: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HostComplianceDto {
+    pub hostname: String,
+    pub desired: DesiredRevisionDto,
+    pub status: ComplianceStatus,
+    pub overdue: bool,
+}
+
+impl From<HostCompliance> for HostComplianceDto {
+    fn from(
+        HostCompliance {
+            hostname,
+            desired,
+            status,
+            overdue,
+        }: HostCompliance,
+    ) -> Self {
+        Self {
+            hostname,
+            desired: desired.into(),
+            status,
+            overdue,
+        }
+    }
+}
@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
 use crate::shared::{
-    dto::activation::ActivationDto,
-    model::{activation::Activation, host::HostModel},
+    dto::{activation::ActivationDto, nix_git_link::LatestBuildDto},
+    model::{
+        activation::{Activation, TRANSIENT_ACTIVATION_TYPES},
+        host::{HostModel, HostWithState},
+    },
 };
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -74,3 +77,194 @@ impl From<HostModel> for CurrentHostDto {
         }
     }
 }
+
+/// A host with the system it runs now in `logs` and the one it boots into next.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HostStateDto {
+    #[serde(flatten)]
+    pub host: CurrentHostDto,
+    pub next_boot: Option<ActivationDto>,
+    /// The next boot brings up another system than the one running.
+    pub reboot_pending: bool,
+    /// The running system came from a `test` and is gone after the next boot.
+    pub running_transient: bool,
+    /// The newest `test`, whether or not it still runs.
+    pub latest_test: Option<ActivationDto>,
+    pub rollbacks: i64,
+    /// The newest system CI built for the host.
+    pub latest_build: Option<LatestBuildDto>,
+    /// Flake input revisions of the running system, by input name.
+    #[serde(default)]
+    pub running_inputs: BTreeMap<String, String>,
+}
+
+impl From<HostWithState> for HostStateDto {
+    fn from(
+        HostWithState {
+            host,
+            running,
+            next_boot,
+            latest_test,
+            rollbacks,
+            latest_build,
+            running_inputs,
+        }: HostWithState,
+    ) -> Self {
+        let reboot_pending = match (&running, &next_boot) {
+            (Some(running), Some(next_boot)) => {
+                running.core.store_path != next_boot.core.store_path
+            }
+            _ => false,
+        };
+        let running_transient = running
+            .as_ref()
+            .is_some_and(|a| TRANSIENT_ACTIVATION_TYPES.contains(&a.core.activation_type.as_str()));
+        let latest_build = latest_build.map(|build| LatestBuildDto::new(build, running.as_ref()));
+        Self {
+            host: CurrentHostDto::from((host, running)),
+            next_boot: next_boot.map(Into::into),
+            reboot_pending,
+            running_transient,
+            latest_test: latest_test.map(Into::into),
+            rollbacks,
+            latest_build,
+            running_inputs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::model::{
+        activation::ActivationCore, nix_git_link::LatestBuild, revision::RevisionModel,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn activation(store_path: &str, activation_type: &str) -> Activation {
+        Activation {
+            id: 1,
+            core: ActivationCore {
+                activated_at: Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap(),
+                username: "root".to_string(),
+                store_path: store_path.to_string(),
+                activation_type: activation_type.to_string(),
+                hostname: "foo".to_string(),
+                revision: None,
+            },
+        }
+    }
+
+    fn host_state(running: Option<Activation>, next_boot: Option<Activation>) -> HostStateDto {
+        let latest_test = running
+            .iter()
+            .chain(&next_boot)
+            .find(|activation| activation.core.activation_type == "test")
+            .cloned();
+        host_state_with_test(running, next_boot, latest_test)
+    }
+
+    fn host_state_with_test(
+        running: Option<Activation>,
+        next_boot: Option<Activation>,
+        latest_test: Option<Activation>,
+    ) -> HostStateDto {
+        HostStateDto::from(HostWithState {
+            host: HostModel {
+                hostname: "foo".to_string(),
+                host_url: "foo.example.com".to_string(),
+                metadata: HashMap::new(),
+            },
+            running,
+            next_boot,
+            latest_test,
+            rollbacks: 0,
+            latest_build: None,
+            running_inputs: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_switch_is_running_and_next_boot() {
+        let state = host_state(
+            Some(activation("/nix/store/a", "switch")),
+            Some(activation("/nix/store/a", "switch")),
+        );
+        assert!(!state.reboot_pending);
+        assert!(!state.running_transient);
+    }
+
+    #[test]
+    fn test_boot_after_switch() {
+        let state = host_state(
+            Some(activation("/nix/store/a", "switch")),
+            Some(activation("/nix/store/b", "boot")),
+        );
+        assert!(state.reboot_pending);
+        assert!(!state.running_transient);
+        assert_eq!(state.host.logs.unwrap().store_path, "/nix/store/a");
+        assert_eq!(state.next_boot.unwrap().store_path, "/nix/store/b");
+    }
+
+    #[test]
+    fn test_test_after_switch() {
+        let state = host_state(
+            Some(activation("/nix/store/b", "test")),
+            Some(activation("/nix/store/a", "switch")),
+        );
+        assert!(state.reboot_pending);
+        assert!(state.running_transient);
+        assert_eq!(state.latest_test.unwrap().store_path, "/nix/store/b");
+    }
+
+    #[test]
+    fn test_switch_after_test() {
+        let state = host_state_with_test(
+            Some(activation("/nix/store/c", "switch")),
+            Some(activation("/nix/store/c", "switch")),
+            Some(activation("/nix/store/b", "test")),
+        );
+        assert!(!state.reboot_pending);
+        assert!(!state.running_transient);
+        assert_eq!(state.host.logs.unwrap().store_path, "/nix/store/c");
+        assert_eq!(state.latest_test.unwrap().store_path, "/nix/store/b");
+    }
+
+    fn latest_build(store_path: &str, commits_behind: Option<i64>) -> LatestBuild {
+        let activated_at = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        LatestBuild {
+            store_path: store_path.to_string(),
+            revision: RevisionModel {
+                commit_hash: "new".to_string(),
+                branch: "master".to_string(),
+            },
+            linked_at: activated_at + Duration::days(3),
+            running_linked_at: commits_behind.map(|_| activated_at - Duration::days(1)),
+            commits_behind,
+        }
+    }
+
+    #[test]
+    fn test_latest_build() {
+        let running = activation("/nix/store/a", "switch");
+
+        let build = LatestBuildDto::new(latest_build("/nix/store/b", Some(2)), Some(&running));
+        assert!(!build.up_to_date);
+        assert_eq!(build.commits_behind, Some(2));
+        assert_eq!(build.days_behind, Some(4));
+
+        // never built on the branch, so only the age of the activation tells
+        let build = LatestBuildDto::new(latest_build("/nix/store/b", None), Some(&running));
+        assert!(!build.up_to_date);
+        assert_eq!(build.commits_behind, None);
+        assert_eq!(build.days_behind, Some(3));
+
+        let build = LatestBuildDto::new(latest_build("/nix/store/a", Some(0)), Some(&running));
+        assert!(build.up_to_date);
+        assert_eq!(build.days_behind, Some(0));
+
+        let build = LatestBuildDto::new(latest_build("/nix/store/b", None), None);
+        assert!(!build.up_to_date);
+        assert_eq!(build.days_behind, None);
+    }
+}
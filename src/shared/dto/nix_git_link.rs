@@ -1,6 +1,15 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::shared::{dto::revision::RevisionDto, model::nix_git_link::NixGitLinkModel};
+use crate::shared::{
+    dto::revision::RevisionDto,
+    model::{
+        activation::Activation,
+        nix_git_link::{FlakeInputModel, LatestBuild, NixGitLinkModel},
+    },
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct NixGitLinkDto {
@@ -10,19 +19,83 @@ pub struct NixGitLinkDto {
     pub revision: RevisionDto,
     #[serde(rename = "created_at")]
     pub deployed_at: chrono::DateTime<chrono::Utc>,
+    /// The host the store path was built for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Revisions of the flake.lock inputs the store path was built with, by input name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+}
+impl NixGitLinkDto {
+    pub fn flake_inputs(&self) -> impl Iterator<Item = FlakeInputModel> + '_ {
+        self.inputs
+            .iter()
+            .map(|(input_name, revision)| FlakeInputModel {
+                store_path: self.nix_store_path.clone(),
+                input_name: input_name.clone(),
+                revision: revision.clone(),
+            })
+    }
 }
+
 impl From<NixGitLinkModel> for NixGitLinkDto {
     fn from(
         NixGitLinkModel {
             nix_store_path,
             revision,
             linked_at: deployed_at,
+            hostname,
         }: NixGitLinkModel,
     ) -> Self {
         Self {
             nix_store_path,
             revision: revision.into(),
             deployed_at,
+            hostname,
+            inputs: BTreeMap::new(),
+        }
+    }
+}
+
+/// The newest system CI built for a host, compared with the one it runs.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LatestBuildDto {
+    pub store_path: String,
+    #[serde(flatten)]
+    pub revision: RevisionDto,
+    pub linked_at: DateTime<Utc>,
+    /// The host runs the latest build, or nothing newer was built for it.
+    pub up_to_date: bool,
+    /// Commits built for the host after the running system, None if CI never
+    /// built the running system on the branch.
+    pub commits_behind: Option<i64>,
+    /// Days between the running system and the latest build.
+    pub days_behind: Option<i64>,
+}
+
+impl LatestBuildDto {
+    pub fn new(build: LatestBuild, running: Option<&Activation>) -> Self {
+        // a rebuild of the running commits for another store path is not newer
+        let up_to_date = build.commits_behind == Some(0)
+            || running.is_some_and(|running| running.core.store_path == build.store_path);
+        let (commits_behind, days_behind) = if up_to_date {
+            (Some(0), Some(0))
+        } else {
+            // the running system may predate the link of its store path
+            let running_since = build
+                .running_linked_at
+                .or(running.map(|running| running.core.activated_at));
+            let days_behind =
+                running_since.map(|since| (build.linked_at - since).num_days().max(0));
+            (build.commits_behind, days_behind)
+        };
+        Self {
+            store_path: build.store_path,
+            revision: build.revision.into(),
+            linked_at: build.linked_at,
+            up_to_date,
+            commits_behind,
+            days_behind,
         }
     }
 }
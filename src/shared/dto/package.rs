@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::{
+    dto::activation::ActivationDto,
+    model::package::{ActivationPackages, PackageChange, PackageDiff, PackageModel},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PackageDto {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+impl From<PackageDto> for PackageModel {
+    fn from(PackageDto { name, version }: PackageDto) -> Self {
+        Self { name, version }
+    }
+}
+
+impl From<PackageModel> for PackageDto {
+    fn from(PackageModel { name, version }: PackageModel) -> Self {
+        Self { name, version }
+    }
+}
+
+/// Body of uploading the packages in the closure of a store path, it replaces
+/// any manifest uploaded for it before.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PackageManifestDto {
+    pub store_path: String,
+    pub packages: Vec<PackageDto>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PackageChangeDto {
+    pub name: String,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+impl From<PackageChange> for PackageChangeDto {
+    fn from(PackageChange { name, from, to }: PackageChange) -> Self {
+        Self { name, from, to }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DiffActivationDto {
+    pub activation_id: i64,
+    pub hostname: String,
+    pub activation: ActivationDto,
+    /// Packages in the manifest of the store path, None if CI uploaded none.
+    pub packages: Option<usize>,
+}
+
+impl From<&ActivationPackages> for DiffActivationDto {
+    fn from(side: &ActivationPackages) -> Self {
+        Self {
+            activation_id: side.activation.id,
+            hostname: side.activation.core.hostname.clone(),
+            activation: side.activation.clone().into(),
+            packages: side.packages.as_ref().map(Vec::len),
+        }
+    }
+}
+
+/// The packages `to` has against `from`, empty when either has no manifest.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PackageDiffDto {
+    pub from: DiffActivationDto,
+    pub to: DiffActivationDto,
+    pub added: Vec<PackageDto>,
+    pub removed: Vec<PackageDto>,
+    pub changed: Vec<PackageChangeDto>,
+}
+
+impl From<(ActivationPackages, ActivationPackages)> for PackageDiffDto {
+    fn from((from, to): (ActivationPackages, ActivationPackages)) -> Self {
+        let diff = match (&from.packages, &to.packages) {
+            (Some(old), Some(new)) => PackageDiff::new(old, new),
+            _ => PackageDiff::default(),
+        };
+        Self {
+            from: (&from).into(),
+            to: (&to).into(),
+            added: diff.added.into_iter().map(Into::into).collect(),
+            removed: diff.removed.into_iter().map(Into::into).collect(),
+            changed: diff.changed.into_iter().map(Into::into).collect(),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::model::rollout::{Rollout, RolloutGroup, RolloutHost};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RolloutHostDto {
+    pub hostname: String,
+    pub switched_at: Option<DateTime<Utc>>,
+    /// Time from the start of the rollout to the switch.
+    pub switched_after_seconds: Option<i64>,
+    pub running: bool,
+}
+
+impl RolloutHostDto {
+    fn new(
+        RolloutHost {
+            hostname,
+            switched_at,
+            running,
+            ..
+        }: RolloutHost,
+        started_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            hostname,
+            switched_after_seconds: switched_at
+                .zip(started_at)
+                .map(|(end, start)| (end - start).num_seconds()),
+            switched_at,
+            running,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RolloutGroupDto {
+    pub name: String,
+    pub total: usize,
+    pub switched: usize,
+    /// Switched hosts in the order they switched, then the rest.
+    pub hosts: Vec<RolloutHostDto>,
+    pub converged_at: Option<DateTime<Utc>>,
+    pub time_to_converge_seconds: Option<i64>,
+}
+
+impl RolloutGroupDto {
+    fn new(group: RolloutGroup, started_at: Option<DateTime<Utc>>) -> Self {
+        let switched = group.switched();
+        Self {
+            total: group.hosts.len(),
+            switched,
+            converged_at: group.converged_at,
+            time_to_converge_seconds: group.time_to_converge.map(|d| d.num_seconds()),
+            name: group.name,
+            hosts: group
+                .hosts
+                .into_iter()
+                .map(|host| RolloutHostDto::new(host, started_at))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RolloutDto {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_hash: Option<String>,
+    pub store_paths: Vec<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub grouping_key: Option<String>,
+    pub groups: Vec<RolloutGroupDto>,
+}
+
+impl From<Rollout> for RolloutDto {
+    fn from(
+        Rollout {
+            commit_hash,
+            store_paths,
+            started_at,
+            grouping_key,
+            groups,
+        }: Rollout,
+    ) -> Self {
+        Self {
+            commit_hash,
+            store_paths,
+            started_at,
+            grouping_key,
+            groups: groups
+                .into_iter()
+                .map(|group| RolloutGroupDto::new(group, started_at))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::shared::{
+    dto::nix_git_link::NixGitLinkDto,
+    model::store_path::{StorePathDetail, StorePathHost},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StorePathDto {
+    pub store_path: String,
+    /// Every revision CI built this store path from, oldest first.
+    pub links: Vec<NixGitLinkDto>,
+    /// Flake input revisions the store path was built with, by input name.
+    pub inputs: BTreeMap<String, String>,
+    pub hosts: Vec<StorePathHost>,
+}
+
+impl From<StorePathDetail> for StorePathDto {
+    fn from(
+        StorePathDetail {
+            store_path,
+            links,
+            inputs,
+            hosts,
+        }: StorePathDetail,
+    ) -> Self {
+        Self {
+            store_path,
+            links: links.into_iter().map(Into::into).collect(),
+            inputs,
+            hosts,
+        }
+    }
+}
@@ -9,6 +9,15 @@ use crate::shared::{
     model::{host::HostModel, revision::RevisionModel},
 };
 
+/// Activation types that change the system running now. `dry-activate` changes nothing.
+pub const RUNNING_ACTIVATION_TYPES: [&str; 2] = ["switch", "test"];
+/// Activation types that change the system the host boots into.
+pub const NEXT_BOOT_ACTIVATION_TYPES: [&str; 2] = ["switch", "boot"];
+/// Activation types that deploy a system, now or on the next boot.
+pub const DEPLOY_ACTIVATION_TYPES: [&str; 3] = ["switch", "boot", "test"];
+/// Activation types that are gone after the next boot.
+pub const TRANSIENT_ACTIVATION_TYPES: [&str; 1] = ["test"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActivationCore {
     pub activated_at: DateTime<Utc>,
@@ -101,3 +110,11 @@ impl From<ActivationWithRevision> for Activation {
         }
     }
 }
+
+/// An activation in the history of a host.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub activation: ActivationWithRevision,
+    /// The host had this store path before and its commit is older than the one it replaced.
+    pub is_rollback: bool,
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+
+/// Narrows down the activity feed, every filter is optional.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityFilter {
+    /// Metadata key and the value hosts must have for it.
+    pub group: Option<(String, String)>,
+    pub username: Option<String>,
+    pub branch: Option<String>,
+    pub activation_type: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActivityRow {
+    pub activation_id: i64,
+    pub activated_at: DateTime<Utc>,
+    pub username: String,
+    pub hostname: String,
+    pub store_path: String,
+    pub activation_type: String,
+    pub commit_hash: Option<String>,
+    pub branch: Option<String>,
+    /// Same for consecutive activations of one store path in the feed.
+    pub island: i64,
+}
+
+/// Consecutive activations of the same store path, newest first.
+#[derive(Debug, Clone)]
+pub struct ActivityEntry {
+    pub store_path: String,
+    pub commit_hash: Option<String>,
+    pub branch: Option<String>,
+    pub activations: Vec<ActivityRow>,
+}
+
+impl ActivityEntry {
+    /// Groups rows ordered by entry into entries.
+    pub fn group_rows(rows: Vec<ActivityRow>) -> Vec<ActivityEntry> {
+        let mut entries: Vec<ActivityEntry> = Vec::new();
+        for row in rows {
+            match entries.last_mut() {
+                Some(entry)
+                    if entry.store_path == row.store_path
+                        && entry.activations[0].island == row.island =>
+                {
+                    entry.activations.push(row)
+                }
+                _ => entries.push(ActivityEntry {
+                    store_path: row.store_path.clone(),
+                    commit_hash: row.commit_hash.clone(),
+                    branch: row.branch.clone(),
+                    activations: vec![row],
+                }),
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row(id: i64, hostname: &str, store_path: &str, island: i64) -> ActivityRow {
+        ActivityRow {
+            activation_id: id,
+            activated_at: Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap()
+                - chrono::Duration::minutes(id),
+            username: "root".to_string(),
+            hostname: hostname.to_string(),
+            store_path: store_path.to_string(),
+            activation_type: "switch".to_string(),
+            commit_hash: None,
+            branch: None,
+            island,
+        }
+    }
+
+    #[test]
+    fn test_group_rows() {
+        let rows = vec![
+            row(1, "a", "/nix/store/new", 0),
+            row(2, "b", "/nix/store/new", 0),
+            row(3, "a", "/nix/store/old", 2),
+            row(4, "c", "/nix/store/new", 1),
+        ];
+        let entries = ActivityEntry::group_rows(rows);
+        let summary: Vec<(&str, usize)> = entries
+            .iter()
+            .map(|e| (e.store_path.as_str(), e.activations.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("/nix/store/new", 2),
+                ("/nix/store/old", 1),
+                ("/nix/store/new", 1)
+            ]
+        );
+    }
+}
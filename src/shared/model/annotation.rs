@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+
+/// A note an operator left on a host, or on one of its activations.
+#[derive(Debug, Clone)]
+pub struct AnnotationModel {
+    pub annotation_id: i64,
+    pub hostname: String,
+    pub activation_id: Option<i64>,
+    /// Who wrote or last edited the note.
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AnnotationModel {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// The fields of a note that are written, on creating or editing it.
+#[derive(Debug, Clone)]
+pub struct AnnotationContent {
+    pub author: String,
+    pub body: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::model::host::HostWithState;
+
+/// What the hosts of a group should run.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DesiredTarget {
+    /// Any store path CI built from the commit, the hash may be a prefix.
+    Commit(String),
+    StorePath(String),
+}
+
+/// The target of the hosts whose metadata has `group_value` for `grouping_key`.
+#[derive(Debug, Clone)]
+pub struct DesiredRevisionModel {
+    pub grouping_key: String,
+    pub group_value: String,
+    pub target: DesiredTarget,
+    /// When the hosts should be on target by.
+    pub deadline: Option<DateTime<Utc>>,
+    pub author: String,
+    pub set_at: DateTime<Utc>,
+}
+
+impl DesiredRevisionModel {
+    pub fn applies_to(&self, host: &HostWithState) -> bool {
+        host.host.metadata.get(&self.grouping_key) == Some(&self.group_value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceStatus {
+    OnTarget,
+    /// Not on target. Ancestry is not known, so this includes hosts on a commit
+    /// newer than the target.
+    Behind,
+    /// Never activated, or runs a store path CI never linked to a commit.
+    Unknown,
+}
+
+impl ComplianceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ComplianceStatus::OnTarget => "on_target",
+            ComplianceStatus::Behind => "behind",
+            ComplianceStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HostCompliance {
+    pub hostname: String,
+    pub desired: DesiredRevisionModel,
+    pub status: ComplianceStatus,
+    /// Past the deadline and not on target.
+    pub overdue: bool,
+}
+
+impl HostCompliance {
+    /// Compliance of a host with the newest target of a group it is in, None if
+    /// no group of it has one. `linked_store_paths` are the store paths CI built
+    /// from each commit target.
+    pub fn new(
+        host: &HostWithState,
+        targets: &[DesiredRevisionModel],
+        linked_store_paths: &HashMap<String, HashSet<String>>,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let desired = targets
+            .iter()
+            .filter(|desired| desired.applies_to(host))
+            .max_by_key(|desired| desired.set_at)?;
+        let status = match (&desired.target, &host.running) {
+            (_, None) => ComplianceStatus::Unknown,
+            (DesiredTarget::StorePath(store_path), Some(running)) => {
+                if running.core.store_path == *store_path {
+                    ComplianceStatus::OnTarget
+                } else {
+                    ComplianceStatus::Behind
+                }
+            }
+            (DesiredTarget::Commit(commit), Some(running)) => {
+                let on_target = linked_store_paths
+                    .get(commit)
+                    .is_some_and(|store_paths| store_paths.contains(&running.core.store_path));
+                if on_target {
+                    ComplianceStatus::OnTarget
+                } else if running.core.revision.is_none() {
+                    ComplianceStatus::Unknown
+                } else {
+                    ComplianceStatus::Behind
+                }
+            }
+        };
+        let overdue = status != ComplianceStatus::OnTarget
+            && desired.deadline.is_some_and(|deadline| deadline < now);
+        Some(Self {
+            hostname: host.host.hostname.clone(),
+            desired: desired.clone(),
+            status,
+            overdue,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::model::{
+        activation::{Activation, ActivationCore},
+        host::HostModel,
+        revision::RevisionModel,
+    };
+    use chrono::{Duration, TimeZone};
+    use std::collections::BTreeMap;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap()
+    }
+
+    fn host(hostname: &str, env: &str, store_path: Option<&str>, linked: bool) -> HostWithState {
+        HostWithState {
+            host: HostModel {
+                hostname: hostname.to_string(),
+                host_url: hostname.to_string(),
+                metadata: HashMap::from([("env".to_string(), env.to_string())]),
+            },
+            running: store_path.map(|store_path| Activation {
+                id: 1,
+                core: ActivationCore {
+                    activated_at: now(),
+                    username: "root".to_string(),
+                    store_path: store_path.to_string(),
+                    activation_type: "switch".to_string(),
+                    hostname: hostname.to_string(),
+                    revision: linked.then(|| RevisionModel {
+                        commit_hash: "old".to_string(),
+                        branch: "master".to_string(),
+                    }),
+                },
+            }),
+            next_boot: None,
+            latest_test: None,
+            rollbacks: 0,
+            latest_build: None,
+            running_inputs: BTreeMap::new(),
+        }
+    }
+
+    fn desired(env: &str, target: DesiredTarget, hours_ago: i64) -> DesiredRevisionModel {
+        DesiredRevisionModel {
+            grouping_key: "env".to_string(),
+            group_value: env.to_string(),
+            target,
+            deadline: Some(now() - Duration::hours(1)),
+            author: "alice".to_string(),
+            set_at: now() - Duration::hours(hours_ago),
+        }
+    }
+
+    #[test]
+    fn test_host_compliance() {
+        let targets = vec![
+            desired(
+                "prod",
+                DesiredTarget::StorePath("/nix/store/a".to_string()),
+                5,
+            ),
+            desired("prod", DesiredTarget::Commit("abc".to_string()), 2),
+            desired(
+                "staging",
+                DesiredTarget::StorePath("/nix/store/s".to_string()),
+                1,
+            ),
+        ];
+        let linked = HashMap::from([(
+            "abc".to_string(),
+            HashSet::from(["/nix/store/b".to_string()]),
+        )]);
+        let status = |host: &HostWithState| {
+            HostCompliance::new(host, &targets, &linked, now()).map(|c| (c.status, c.overdue))
+        };
+
+        // the newer commit target of prod wins over its store path target
+        assert_eq!(
+            status(&host("p1", "prod", Some("/nix/store/b"), true)),
+            Some((ComplianceStatus::OnTarget, false))
+        );
+        // behind means not on target, whether its commit is older or newer
+        assert_eq!(
+            status(&host("p2", "prod", Some("/nix/store/a"), true)),
+            Some((ComplianceStatus::Behind, true))
+        );
+        assert_eq!(
+            status(&host("p3", "prod", Some("/nix/store/x"), false)),
+            Some((ComplianceStatus::Unknown, true))
+        );
+        assert_eq!(
+            status(&host("p4", "prod", None, false)),
+            Some((ComplianceStatus::Unknown, true))
+        );
+        assert_eq!(
+            status(&host("s1", "staging", Some("/nix/store/s"), false)),
+            Some((ComplianceStatus::OnTarget, false))
+        );
+        assert_eq!(status(&host("d1", "dev", Some("/nix/store/a"), true)), None);
+    }
+}
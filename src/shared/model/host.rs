@@ -1,7 +1,11 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::shared::{dto::host::CurrentHostDto, model::activation::Activation};
+use crate::shared::{
+    dto::host::CurrentHostDto,
+    model::{activation::Activation, nix_git_link::LatestBuild},
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HostModel {
@@ -28,7 +32,26 @@ impl From<CurrentHostDto> for HostModel {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HostWithLatestLog {
+pub struct HostWithState {
     pub host: HostModel,
-    pub logs: Option<Activation>,
+    /// Latest `switch` or `test`.
+    pub running: Option<Activation>,
+    /// Latest `switch` or `boot`.
+    pub next_boot: Option<Activation>,
+    /// Latest `test`, kept after a later `switch` or reboot replaced it.
+    pub latest_test: Option<Activation>,
+    pub rollbacks: i64,
+    pub latest_build: Option<LatestBuild>,
+    /// Flake input revisions of the running system, by input name.
+    pub running_inputs: BTreeMap<String, String>,
+}
+
+/// What the server's `/metrics` needs to know about a host.
+#[derive(Debug, Clone)]
+pub struct HostMetricsRow {
+    pub hostname: String,
+    pub retired: bool,
+    pub activated_at: Option<DateTime<Utc>>,
+    pub commit_hash: Option<String>,
+    pub branch: Option<String>,
 }
@@ -1,4 +1,10 @@
 pub mod activation;
+pub mod activity;
+pub mod annotation;
+pub mod desired_revision;
 pub mod host;
 pub mod nix_git_link;
+pub mod package;
 pub mod revision;
+pub mod rollout;
+pub mod store_path;
@@ -7,6 +7,8 @@ pub struct NixGitLinkModel {
     pub nix_store_path: String,
     pub revision: RevisionModel,
     pub linked_at: chrono::DateTime<chrono::Utc>,
+    /// The host CI built the store path for, if it said.
+    pub hostname: Option<String>,
 }
 impl From<NixGitLinkDto> for NixGitLinkModel {
     fn from(
@@ -14,12 +16,38 @@ impl From<NixGitLinkDto> for NixGitLinkModel {
             nix_store_path,
             revision,
             deployed_at,
+            hostname,
+            ..
         }: NixGitLinkDto,
     ) -> Self {
         Self {
             nix_store_path,
             revision: revision.into(),
             linked_at: deployed_at,
+            hostname,
         }
     }
 }
+
+/// The revision of an input in the flake.lock a store path was built with.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FlakeInputModel {
+    pub store_path: String,
+    /// Name of the input in the flake, e.g. `nixpkgs`.
+    pub input_name: String,
+    pub revision: String,
+}
+
+/// The newest system CI built for a host, on the branch of highest precedence
+/// it built one on.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LatestBuild {
+    pub store_path: String,
+    pub revision: RevisionModel,
+    pub linked_at: chrono::DateTime<chrono::Utc>,
+    /// When CI built the store path the host runs on the same branch.
+    pub running_linked_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Commits CI built for the host on the branch after the running system,
+    /// None if it never built the running system on the branch.
+    pub commits_behind: Option<i64>,
+}
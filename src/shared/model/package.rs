@@ -0,0 +1,134 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::shared::model::activation::Activation;
+
+/// A package in the closure of a store path, `version` is empty for those without one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackageModel {
+    pub name: String,
+    pub version: String,
+}
+
+/// A package in both closures with different versions. A closure can hold
+/// several versions of one package, so both sides list all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageChange {
+    pub name: String,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+/// The packages of one closure against another, each sorted by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageDiff {
+    pub added: Vec<PackageModel>,
+    pub removed: Vec<PackageModel>,
+    pub changed: Vec<PackageChange>,
+}
+
+impl PackageDiff {
+    pub fn new(from: &[PackageModel], to: &[PackageModel]) -> Self {
+        let from = versions_by_name(from);
+        let to = versions_by_name(to);
+        let mut diff = Self::default();
+        for (name, versions) in &to {
+            match from.get(name) {
+                None => diff.added.extend(packages(name, versions)),
+                Some(old) if old != versions => diff.changed.push(PackageChange {
+                    name: name.to_string(),
+                    from: old.iter().map(|v| v.to_string()).collect(),
+                    to: versions.iter().map(|v| v.to_string()).collect(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (name, versions) in &from {
+            if !to.contains_key(name) {
+                diff.removed.extend(packages(name, versions));
+            }
+        }
+        diff
+    }
+}
+
+fn versions_by_name(packages: &[PackageModel]) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut versions: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for package in packages {
+        versions
+            .entry(&package.name)
+            .or_default()
+            .insert(&package.version);
+    }
+    versions
+}
+
+fn packages<'a>(
+    name: &'a str,
+    versions: &'a BTreeSet<&str>,
+) -> impl Iterator<Item = PackageModel> + 'a {
+    versions.iter().map(move |version| PackageModel {
+        name: name.to_string(),
+        version: version.to_string(),
+    })
+}
+
+/// One side of a diff: an activation and the manifest of its store path, None
+/// if CI never uploaded one.
+#[derive(Debug, Clone)]
+pub struct ActivationPackages {
+    pub activation: Activation,
+    pub packages: Option<Vec<PackageModel>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(packages: &[(&str, &str)]) -> Vec<PackageModel> {
+        packages
+            .iter()
+            .map(|(name, version)| PackageModel {
+                name: name.to_string(),
+                version: version.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_package_diff() {
+        let from = manifest(&[
+            ("bash", "5.2p37"),
+            ("openssl", "3.0.15"),
+            ("python3", "3.11.10"),
+            ("python3", "3.12.8"),
+            ("nginx", "1.26.2"),
+            ("etc", ""),
+        ]);
+        let to = manifest(&[
+            ("etc", ""),
+            ("bash", "5.2p37"),
+            ("openssl", "3.0.16"),
+            ("python3", "3.12.8"),
+            ("caddy", "2.9.1"),
+        ]);
+        let diff = PackageDiff::new(&from, &to);
+        assert_eq!(diff.added, manifest(&[("caddy", "2.9.1")]));
+        assert_eq!(diff.removed, manifest(&[("nginx", "1.26.2")]));
+        assert_eq!(
+            diff.changed,
+            vec![
+                PackageChange {
+                    name: "openssl".to_string(),
+                    from: vec!["3.0.15".to_string()],
+                    to: vec!["3.0.16".to_string()],
+                },
+                PackageChange {
+                    name: "python3".to_string(),
+                    from: vec!["3.11.10".to_string(), "3.12.8".to_string()],
+                    to: vec!["3.12.8".to_string()],
+                },
+            ]
+        );
+        assert_eq!(PackageDiff::new(&to, &to), PackageDiff::default());
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A host that is not retired, and when it first switched to the rollout.
+#[derive(Debug, Clone)]
+pub struct RolloutHost {
+    pub hostname: String,
+    pub metadata: HashMap<String, String>,
+    pub switched_at: Option<DateTime<Utc>>,
+    /// Still running one of the store paths of the rollout.
+    pub running: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RolloutGroup {
+    pub name: String,
+    /// Switched hosts in the order they switched, then the rest by name.
+    pub hosts: Vec<RolloutHost>,
+    /// When the last host of the group switched, once every host has.
+    pub converged_at: Option<DateTime<Utc>>,
+    /// From the start of the rollout to `converged_at`.
+    pub time_to_converge: Option<Duration>,
+}
+
+impl RolloutGroup {
+    pub fn switched(&self) -> usize {
+        self.hosts
+            .iter()
+            .filter(|host| host.switched_at.is_some())
+            .count()
+    }
+}
+
+/// How far the store paths of a commit, or a set of store paths, have spread.
+#[derive(Debug, Clone)]
+pub struct Rollout {
+    pub commit_hash: Option<String>,
+    pub store_paths: Vec<String>,
+    /// When CI first linked the rollout, or the first switch to it if it never did.
+    pub started_at: Option<DateTime<Utc>>,
+    pub grouping_key: Option<String>,
+    pub groups: Vec<RolloutGroup>,
+}
+
+impl Rollout {
+    pub fn new(
+        commit_hash: Option<String>,
+        store_paths: Vec<String>,
+        linked_at: Option<DateTime<Utc>>,
+        hosts: Vec<RolloutHost>,
+        grouping_key: Option<String>,
+    ) -> Self {
+        let started_at =
+            linked_at.or_else(|| hosts.iter().filter_map(|host| host.switched_at).min());
+
+        let mut by_group: BTreeMap<String, Vec<RolloutHost>> = BTreeMap::new();
+        for host in hosts {
+            let name = grouping_key
+                .as_ref()
+                .map(|key| {
+                    host.metadata
+                        .get(key)
+                        .cloned()
+                        .unwrap_or_else(|| "Ungrouped".to_string())
+                })
+                .unwrap_or_else(|| "all hosts".to_string());
+            by_group.entry(name).or_default().push(host);
+        }
+
+        let groups = by_group
+            .into_iter()
+            .map(|(name, mut hosts)| {
+                hosts.sort_by(|a, b| {
+                    (a.switched_at.is_none(), a.switched_at, &a.hostname).cmp(&(
+                        b.switched_at.is_none(),
+                        b.switched_at,
+                        &b.hostname,
+                    ))
+                });
+                let converged_at = hosts
+                    .iter()
+                    .map(|host| host.switched_at)
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|switched| switched.into_iter().max());
+                let time_to_converge = converged_at.zip(started_at).map(|(end, start)| end - start);
+                RolloutGroup {
+                    name,
+                    hosts,
+                    converged_at,
+                    time_to_converge,
+                }
+            })
+            .collect();
+
+        Self {
+            commit_hash,
+            store_paths,
+            started_at,
+            grouping_key,
+            groups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn host(hostname: &str, env: &str, switched_minutes: Option<i64>) -> RolloutHost {
+        let start = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        RolloutHost {
+            hostname: hostname.to_string(),
+            metadata: HashMap::from([("env".to_string(), env.to_string())]),
+            switched_at: switched_minutes.map(|m| start + Duration::minutes(m)),
+            running: switched_minutes.is_some(),
+        }
+    }
+
+    #[test]
+    fn test_rollout_groups() {
+        let linked_at = Utc.with_ymd_and_hms(2026, 1, 5, 12, 0, 0).unwrap();
+        let rollout = Rollout::new(
+            Some("abc".to_string()),
+            vec![],
+            Some(linked_at),
+            vec![
+                host("p1", "prod", None),
+                host("p2", "prod", Some(30)),
+                host("s2", "staging", Some(20)),
+                host("s1", "staging", Some(5)),
+            ],
+            Some("env".to_string()),
+        );
+        let prod = &rollout.groups[0];
+        assert_eq!(prod.name, "prod");
+        assert_eq!(prod.switched(), 1);
+        assert_eq!(prod.hosts[1].hostname, "p1");
+        assert_eq!(prod.converged_at, None);
+        assert_eq!(prod.time_to_converge, None);
+
+        let staging = &rollout.groups[1];
+        assert_eq!(staging.hosts[0].hostname, "s1");
+        assert_eq!(staging.time_to_converge, Some(Duration::minutes(20)));
+    }
+
+    #[test]
+    fn test_rollout_never_linked_starts_at_first_switch() {
+        let rollout = Rollout::new(
+            None,
+            vec![],
+            None,
+            vec![host("a", "prod", Some(10)), host("b", "prod", Some(25))],
+            None,
+        );
+        assert_eq!(rollout.groups[0].name, "all hosts");
+        assert_eq!(
+            rollout.groups[0].time_to_converge,
+            Some(Duration::minutes(15))
+        );
+    }
+}
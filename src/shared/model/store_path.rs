@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::shared::model::nix_git_link::NixGitLinkModel;
+
+/// A host that deployed a store path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorePathHost {
+    pub hostname: String,
+    pub first_activated_at: DateTime<Utc>,
+    pub last_activated_at: DateTime<Utc>,
+    pub activations: i64,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorePathDetail {
+    pub store_path: String,
+    pub links: Vec<NixGitLinkModel>,
+    /// Flake input revisions the store path was built with, by input name.
+    pub inputs: BTreeMap<String, String>,
+    pub hosts: Vec<StorePathHost>,
+}